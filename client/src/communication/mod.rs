@@ -1,2 +1,8 @@
+use client_lib::codec::JsonCodec;
+
 pub mod net;
 pub mod tui;
+
+//peers never negotiate a codec and may run other builds, so chat payloads stay json;
+//fields added since are defaulted when missing, builds older than them can't read them
+pub(crate) const CHAT_CODEC: JsonCodec = JsonCodec;
@@ -1,6 +1,4 @@
 use crate::network::Network;
-use client_lib::communication::send_message;
-use client_lib::communication::TUICommand::Kill;
 use common_structs::leaf::LeafCommand;
 use common_structs::types::*;
 use std::sync::MutexGuard;
@@ -20,9 +18,7 @@ pub(crate) fn handle_command(net: &mut MutexGuard<Network>, c: LeafCommand) -> b
             net.add_sender(conn_id, sender);
         }
         LeafCommand::Kill => {
-            if let Some(stream) = &mut net.frontend_stream {
-                let _ = send_message(stream, Kill);
-            }
+            net.close_frontend();
             return true;
         }
     }
@@ -1,22 +1,36 @@
-use crate::network::Network;
-use client_lib::communication::MessageStatus::ReadByPeer;
+use crate::communication::CHAT_CODEC;
+use crate::network::{Network, SessionKind};
+use client_lib::codec::Codec;
+use client_lib::communication::MessageStatus::{ReadByPeer, SentToServer};
 use client_lib::communication::TUICommand::{
-    UpdateMessageContent, UpdateMessageReaction, UpdateMessageStatus, UpdateName,
+    UpdateMessageContent, UpdateMessageReaction, UpdateMessageStatus,
 };
 use client_lib::communication::TUIEvent::*;
-use client_lib::communication::{receive_message, send_message, TUICommand, TUIEvent};
+use client_lib::communication::{now, Connection, TUICommand, TUIEvent};
 use client_lib::ClientError;
-use client_lib::ClientError::LockError;
+use client_lib::ClientError::{Decode, LockError};
 use common_structs::message::Message::{ReqChatClients, ReqChatRegistration, ReqChatSend};
-use std::net::TcpStream;
 use std::sync::{Arc, Mutex};
 
-pub(crate) fn tui_event_receiver(state: Arc<Mutex<Network>>, mut stream: TcpStream) {
+pub(crate) fn tui_event_receiver(
+    state: Arc<Mutex<Network>>,
+    mut stream: Connection,
+    generation: u64,
+) {
     loop {
-        match receive_message::<TUIEvent>(&mut stream) {
+        match stream.receive::<TUIEvent>() {
+            //a single bad frame is skipped, the stream is still in sync
+            Err(Decode(_)) => {}
+            //the frontend quit or is gone, keep running until a new one attaches
+            Ok(Dead) | Err(_) => {
+                if let Ok(mut state) = state.lock() {
+                    state.detach_frontend(generation);
+                }
+                break;
+            }
             Ok(event) => {
                 //println!("Backend: received event: {:?}", event);
-                match handle_tui_event(&state, &mut stream, event) {
+                match handle_tui_event(&state, event) {
                     Ok(_) => {
                         //println!("Event handled correctly")
                     }
@@ -25,80 +39,77 @@ pub(crate) fn tui_event_receiver(state: Arc<Mutex<Network>>, mut stream: TcpStre
                     }
                 };
             }
-            Err(e) => {
-                //println!("Backend: Error reading event: {:?}", e);
-                break;
-            }
         }
     }
 }
 
-fn handle_tui_event(
-    state: &Arc<Mutex<Network>>,
-    stream: &mut TcpStream,
-    event: TUIEvent,
-) -> Result<(), ClientError> {
+fn handle_tui_event(state: &Arc<Mutex<Network>>, event: TUIEvent) -> Result<(), ClientError> {
     let mut state = state.lock().map_err(|_| LockError)?;
     match event {
         SetName(s) => {
-            //TODO: send new name to each known peer;
-            send_message(stream, UpdateName(s))?;
+            state.set_name(s);
         }
         RegisterToServer(cr) => {
             state.send_message(ReqChatRegistration, cr, None);
         }
         DeleteMessage(cr, cl, cm) => {
+            state.remember_frontend(TUICommand::DeleteMessage(cr, cl, cm));
             let command = TUICommand::DeleteMessage(cr, state.id, cm);
-            let content = serde_json::to_string(&command)
-                .unwrap()
-                .as_bytes()
-                .to_owned();
+            let content = CHAT_CODEC.encode(&command)?;
             let message = ReqChatSend {
                 to: cl,
                 chat_msg: content,
             };
-            state.send_message(message, cr, Some(cm));
+            state.send_message(message, cr, Some((cm, SessionKind::Delete)));
         }
         SendMessage(cr, cl, cm, mc) => {
-            let command = UpdateMessageContent(cr, state.id, cm, mc);
-            let content = serde_json::to_string(&command)
-                .unwrap()
-                .as_bytes()
-                .to_owned();
+            let sent = now();
+            state.remember_frontend(UpdateMessageContent(cr, cl, cm, mc.clone(), sent));
+            state.remember_frontend(UpdateMessageStatus(cr, cl, cm, SentToServer));
+            let command = UpdateMessageContent(cr, state.id, cm, mc, sent);
+            let content = CHAT_CODEC.encode(&command)?;
+            let message = ReqChatSend {
+                to: cl,
+                chat_msg: content,
+            };
+            state.send_message(message, cr, Some((cm, SessionKind::Content)));
+        }
+        EditMessage(cr, cl, cm, mc) => {
+            //the peer takes new content for a message it knows as an edit
+            let edited = now();
+            state.remember_frontend(UpdateMessageContent(cr, cl, cm, mc.clone(), edited));
+            let command = UpdateMessageContent(cr, state.id, cm, mc, edited);
+            let content = CHAT_CODEC.encode(&command)?;
             let message = ReqChatSend {
                 to: cl,
                 chat_msg: content,
             };
-            state.send_message(message, cr, Some(cm));
+            state.send_message(message, cr, Some((cm, SessionKind::Edit)));
         }
         ReadMessage(cr, cl, cm) => {
             let command = UpdateMessageStatus(cr, state.id, cm, ReadByPeer);
-            let content = serde_json::to_string(&command)
-                .unwrap()
-                .as_bytes()
-                .to_owned();
+            let content = CHAT_CODEC.encode(&command)?;
             let message = ReqChatSend {
                 to: cl,
                 chat_msg: content,
             };
-            state.send_message(message, cr, Some(cm));
+            state.send_message(message, cr, Some((cm, SessionKind::Read)));
         }
         ReactToMessage(cr, cl, cm, reaction) => {
-            let command = UpdateMessageReaction(cr, state.id, cm, Some(reaction));
-            let content = serde_json::to_string(&command)
-                .unwrap()
-                .as_bytes()
-                .to_owned();
+            state.remember_frontend(UpdateMessageReaction(cr, cl, cm, reaction));
+            let command = UpdateMessageReaction(cr, state.id, cm, reaction);
+            let content = CHAT_CODEC.encode(&command)?;
             let message = ReqChatSend {
                 to: cl,
                 chat_msg: content,
             };
-            state.send_message(message, cr, Some(cm));
+            state.send_message(message, cr, Some((cm, SessionKind::Reaction)));
         }
         RequestRoomList(cr) => {
             let message = ReqChatClients;
             state.send_message(message, cr, None);
         }
+        //the receiver stops on it
         Dead => {}
     }
     Ok(())
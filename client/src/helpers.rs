@@ -1,55 +1,82 @@
-use client_lib::sys::open_terminal_with_command;
+use client_lib::auth::TOKEN_ENV;
+use client_lib::sys::{LaunchChoice, Launcher, Terminal};
+use client_lib::transport::{Endpoint, Listener, Transport};
 use client_lib::ClientError;
 use client_lib::ClientError::{EnvError, ListenerError};
 use std::env;
 use std::env::current_exe;
-use std::net::{TcpListener, TcpStream};
-
-pub(crate) fn new_listener() -> Result<TcpListener, ClientError> {
-    let listener = match TcpListener::bind("127.0.0.1:0") {
-        Ok(listener) => listener,
-        Err(_) => {
-            return Err(ListenerError);
-        }
-    };
 
-    // debug
-    let listener_port = match listener.local_addr() {
-        Ok(local_addr) => local_addr,
-        Err(_) => {
-            return Err(ListenerError);
-        }
-    };
-    //println!("CLIENT port: {}", listener_port.port());
+//where the client gets its frontend from
+pub enum FrontendMode {
+    //opens a terminal running the TUI, other frontends may connect later on the same socket
+    Terminal,
+    //uses an already connected frontend
+    Stream(Box<dyn Transport>),
+    //waits for frontends on the listener, each new one replaces the previous
+    Listen(Box<dyn Listener>),
+    //never has a frontend
+    Headless,
+}
+
+//a unix socket only the current user can connect to, tcp on localhost where there are none
+#[cfg(unix)]
+pub(crate) fn new_listener() -> Result<Box<dyn Listener>, ClientError> {
+    use client_lib::transport::UnixSocketListener;
+
+    match UnixSocketListener::bind_private() {
+        Ok(listener) => Ok(Box::new(listener)),
+        Err(_) => Err(ListenerError),
+    }
+}
 
-    Ok(listener)
+#[cfg(not(unix))]
+pub(crate) fn new_listener() -> Result<Box<dyn Listener>, ClientError> {
+    match std::net::TcpListener::bind("127.0.0.1:0") {
+        Ok(listener) => Ok(Box::new(listener)),
+        Err(_) => Err(ListenerError),
+    }
 }
 
-pub(crate) fn start_tui(listener: &TcpListener) -> Result<(), ClientError> {
+//the token reaches the TUI through its environment, never its arguments
+pub(crate) fn start_tui(
+    listener: &dyn Listener,
+    token: &str,
+    terminal: Option<Terminal>,
+) -> Result<LaunchChoice, ClientError> {
     env::set_var("RUST_BACKTRACE", "1");
 
-    let listener_port = match listener.local_addr() {
-        Ok(local_addr) => local_addr,
-        Err(_) => {
-            return Err(ListenerError);
-        }
+    let Some(endpoint) = listener.endpoint() else {
+        return Err(ListenerError);
+    };
+
+    let command = tui_command(&endpoint)?;
+    let mut launcher = Launcher::new();
+    if let Some(terminal) = terminal {
+        launcher = launcher.with_terminal(terminal);
     }
-    .port();
+    Ok(launcher.launch(&command, &[(TOKEN_ENV, token)]))
+}
 
-    let mut tui_exe = match current_exe() {
-        Ok(tui_exe) => tui_exe,
+//the chat-tui binary installed next to this one, or this executable if it embeds the TUI itself
+fn tui_command(endpoint: &Endpoint) -> Result<Vec<String>, ClientError> {
+    let exe = match current_exe() {
+        Ok(exe) => exe,
         Err(_) => return Err(EnvError),
     };
-
-    match open_terminal_with_command(&format!("{} chat-tui {}", tui_exe.display(), listener_port)) {
-        Ok(_) => Ok(()),
-        Err(_) => Err(EnvError),
+    let chat_tui = exe.with_file_name(format!("chat-tui{}", env::consts::EXE_SUFFIX));
+    if chat_tui.is_file() {
+        return Ok(vec![chat_tui.display().to_string(), endpoint.to_string()]);
     }
+    Ok(vec![
+        exe.display().to_string(),
+        "chat-tui".to_string(),
+        endpoint.to_string(),
+    ])
 }
 
-pub(crate) fn get_stream(listener: TcpListener) -> Result<TcpStream, ClientError> {
-    match listener.accept() {
-        Ok((stream, _)) => Ok(stream),
+pub(crate) fn get_stream(listener: &dyn Listener) -> Result<Box<dyn Transport>, ClientError> {
+    match listener.accept_transport() {
+        Ok(stream) => Ok(stream),
         Err(_) => Err(ListenerError),
     }
 }
@@ -5,22 +5,159 @@ mod network;
 use crate::communication::net::*;
 use crate::communication::tui::tui_event_receiver;
 use crate::helpers::{get_stream, new_listener, start_tui};
-use crate::network::Network;
-use client_lib::communication::send_message;
-use client_lib::communication::TUICommand::UpdateName;
-use client_lib::ClientError::{LockError, StreamError};
+use crate::network::{Network, RETRANSMISSION_TICK};
+use client_lib::auth::{new_token, Auth};
+use client_lib::communication::{handshake, Capability};
+use client_lib::sys::{LaunchChoice, Terminal};
+use client_lib::transport::{AcceptWaker, Listener, Transport};
+use client_lib::ClientError;
+use client_lib::ClientError::LockError;
 use common_structs::leaf::{Leaf, LeafCommand, LeafEvent};
-use crossbeam_channel::{select, Receiver, Sender};
+use crossbeam_channel::{select, tick, Receiver, Sender};
 use std::collections::HashMap;
+use std::env;
+use std::mem;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use wg_2024::network::NodeId;
 use wg_2024::packet::Packet;
 
+pub use crate::helpers::FrontendMode;
+pub use crate::network::{FloodConfig, FloodStats, ReassemblyStats};
+
+//features of the TUI protocol this backend supports
+const FRONTEND_CAPABILITIES: &[Capability] = &[
+    Capability::Drawings,
+    Capability::Reactions,
+    Capability::BinaryCodec,
+];
+
+//how long a killed client waits for each thread talking to frontends
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+//directory where clients keep what must survive a restart, e.g. the chosen name
+pub const DATA_DIR_ENV: &str = "PICTORUST_DATA_DIR";
+
 pub struct Client {
     controller_recv: Receiver<LeafCommand>,
     packet_recv: Receiver<Packet>,
+    tick_recv: Receiver<Instant>,
     network: Arc<Mutex<Network>>,
+    frontend: FrontendMode,
+    //frontends must present it in their hello, if set
+    frontend_token: Option<String>,
+    //where the TUI is opened in terminal mode, found by the launcher if not set
+    terminal: Option<Terminal>,
+    frontend_started: bool,
+    //the thread attaching frontends and how it is woken up
+    acceptor: Option<(JoinHandle<()>, AcceptWaker)>,
+}
+
+impl Client {
+    //replaces the retransmission clock, e.g. with a manually driven channel in tests
+    pub fn with_tick_receiver(mut self, tick_recv: Receiver<Instant>) -> Self {
+        self.tick_recv = tick_recv;
+        self
+    }
+
+    //chooses how the frontend is reached, by default a terminal running the TUI is opened
+    pub fn with_frontend(mut self, frontend: FrontendMode) -> Self {
+        self.frontend = frontend;
+        self
+    }
+
+    //refuses frontends that do not present the token, a terminal started by the client
+    //always gets one, random if not given
+    pub fn with_frontend_token(mut self, token: String) -> Self {
+        self.frontend_token = Some(token);
+        self
+    }
+
+    //opens the TUI in this terminal, unless overridden by the environment
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.terminal = Some(terminal);
+        self
+    }
+
+    //keeps what must survive a restart in the directory, overriding the env var
+    pub fn with_data_dir(self, dir: PathBuf) -> Self {
+        self.network
+            .lock()
+            .map_err(|_| LockError)
+            .unwrap()
+            .set_data_dir(dir);
+        self
+    }
+
+    //changes how often the client is allowed to flood the network
+    pub fn with_flood_config(self, config: FloodConfig) -> Self {
+        self.network
+            .lock()
+            .map_err(|_| LockError)
+            .unwrap()
+            .set_flood_config(config);
+        self
+    }
+
+    //opens the terminal or starts listening for frontends, as chosen with `with_frontend`,
+    //returning where a terminal went: the attach line to show if none could be opened.
+    //run() does it if it was not done before, printing that line to stderr
+    pub fn start_frontend(&mut self) -> Result<Option<LaunchChoice>, ClientError> {
+        if self.frontend_started {
+            return Ok(None);
+        }
+        self.frontend_started = true;
+        self.network.lock().map_err(|_| LockError)?.load_name();
+
+        let mut choice = None;
+        self.acceptor = match mem::replace(&mut self.frontend, FrontendMode::Headless) {
+            FrontendMode::Terminal => {
+                let token = self.frontend_token.get_or_insert_with(new_token).clone();
+                let listener = new_listener()?;
+                choice = Some(start_tui(listener.as_ref(), &token, self.terminal.take())?);
+                Some(accept_frontends(&self.network, listener, Some(token)))
+            }
+            FrontendMode::Stream(stream) => {
+                //handshaken on its own thread, as a listener's frontends, so run() can read a kill
+                let network = Arc::clone(&self.network);
+                let token = self.frontend_token.clone();
+                let thread = thread::spawn(move || {
+                    attach_frontend(&network, stream, token.as_deref());
+                });
+                //nothing to wake up, a kill closes the stream
+                let waker: AcceptWaker = Box::new(|| {});
+                Some((thread, waker))
+            }
+            FrontendMode::Listen(listener) => Some(accept_frontends(
+                &self.network,
+                listener,
+                self.frontend_token.clone(),
+            )),
+            FrontendMode::Headless => None,
+        };
+        Ok(choice)
+    }
+
+    //counters of the floods started and suppressed so far
+    pub fn flood_stats(&self) -> FloodStats {
+        self.network
+            .lock()
+            .map_err(|_| LockError)
+            .unwrap()
+            .flood_stats()
+    }
+
+    //counters of the incomplete messages discarded so far
+    pub fn reassembly_stats(&self) -> ReassemblyStats {
+        self.network
+            .lock()
+            .map_err(|_| LockError)
+            .unwrap()
+            .reassembly_stats()
+    }
 }
 
 impl Leaf for Client {
@@ -34,46 +171,41 @@ impl Leaf for Client {
     where
         Self: Sized,
     {
+        let mut network = Network::new(id, packet_send, controller_send);
+        if let Some(dir) = env::var_os(DATA_DIR_ENV) {
+            network.set_data_dir(dir.into());
+        }
         Client {
             controller_recv,
             packet_recv,
-            network: Arc::new(Mutex::new(Network::new(id, packet_send, controller_send))),
+            tick_recv: tick(RETRANSMISSION_TICK),
+            network: Arc::new(Mutex::new(network)),
+            frontend: FrontendMode::Terminal,
+            frontend_token: None,
+            terminal: None,
+            frontend_started: false,
+            acceptor: None,
         }
     }
 
     fn run(&mut self) {
         //START CLIENT TUI AND GET TCP CONNECTION TO IT
-        let listener = new_listener().unwrap();
-        start_tui(&listener).unwrap();
-        let events_frontend_stream = get_stream(listener).unwrap();
-        let mut net = self.network.lock().unwrap();
-        net.frontend_stream = Some(
-            events_frontend_stream
-                .try_clone()
-                .map_err(|_| StreamError)
-                .unwrap(),
-        );
-        let id = net.get_id();
-        if let Some(stream) = &mut net.frontend_stream {
-            let _ = send_message(stream, UpdateName(format!("client_{}", id)));
+        match self.start_frontend() {
+            Ok(Some(LaunchChoice::Print(attach_line))) => {
+                eprintln!("To open the chat, run: {}", attach_line);
+            }
+            Ok(_) => {}
+            Err(error) => eprintln!("The chat frontend could not be started: {}", error),
         }
-        drop(net);
+        let acceptor = self.acceptor.take();
 
-        //INITIALIZE STATE
-        let net_front = Arc::clone(&self.network);
         let net_back = Arc::clone(&self.network);
-
-        //TUI EVENT RECEIVER THREAD
-        thread::spawn(move || {
-            tui_event_receiver(net_front, events_frontend_stream);
-        });
-
         let mut exit = false;
         net_back
             .lock()
             .map_err(|_| LockError)
             .unwrap()
-            .initiate_flood();
+            .request_flood();
         while !exit {
             select! {
                 recv(self.controller_recv) -> msg =>{
@@ -91,8 +223,106 @@ impl Leaf for Client {
                     }
                     net_back.handle_packet(packet);
                     drop(net_back);
+                },
+                recv(self.tick_recv) -> msg => {
+                    let mut net_back = net_back.lock().map_err(|_| LockError).unwrap();
+                    let Ok(now) = msg else {continue;};
+                    net_back.handle_tick(now);
+                    drop(net_back);
                 }
             }
         }
+
+        //KILLED: the frontend was told, wait for the threads talking to frontends;
+        //a connection wakes the listener up to see the client is closing,
+        //a thread still stuck after the timeout is left behind
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        if let Some((acceptor, waker)) = acceptor {
+            waker();
+            join_within(acceptor, deadline);
+        }
+        let threads = net_back
+            .lock()
+            .map_err(|_| LockError)
+            .unwrap()
+            .take_frontend_threads();
+        for thread in threads {
+            join_within(thread, deadline);
+        }
+    }
+}
+
+//joins the thread if it ends before the deadline
+fn join_within(thread: JoinHandle<()>, deadline: Instant) {
+    while !thread.is_finished() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(10));
+    }
+    if thread.is_finished() {
+        let _ = thread.join();
+    }
+}
+
+//hands the stream to the network and starts listening to its events
+fn attach_frontend(network: &Arc<Mutex<Network>>, stream: Box<dyn Transport>, token: Option<&str>) {
+    let auth = match token {
+        Some(token) => Auth::Require(token),
+        None => Auth::Open,
+    };
+    //a kill closes the stream, so a frontend stalling the handshake can't hold the client
+    let Ok(handle) = stream.try_clone_boxed() else {
+        return;
+    };
+    if !network
+        .lock()
+        .map_err(|_| LockError)
+        .unwrap()
+        .start_handshake(handle)
+    {
+        return;
     }
+    //frontends from an incompatible build or without the token are refused
+    let handshaken = handshake(stream, FRONTEND_CAPABILITIES, auth);
+    network
+        .lock()
+        .map_err(|_| LockError)
+        .unwrap()
+        .end_handshake();
+    let Ok(stream) = handshaken else {
+        return;
+    };
+    let Ok(events_stream) = stream.try_clone() else {
+        return;
+    };
+    //held until the thread is recorded, so a kill in between can't miss it
+    let mut net = network.lock().map_err(|_| LockError).unwrap();
+    let Some(generation) = net.attach_frontend(stream) else {
+        return;
+    };
+
+    //TUI EVENT RECEIVER THREAD
+    let net_front = Arc::clone(network);
+    let thread = thread::spawn(move || {
+        tui_event_receiver(net_front, events_stream, generation);
+    });
+    net.add_frontend_thread(thread);
+}
+
+//attaches every frontend connecting to the listener, the newest one replacing the others,
+//until the client is closing; returns the thread and what wakes the listener up
+fn accept_frontends(
+    network: &Arc<Mutex<Network>>,
+    listener: Box<dyn Listener>,
+    token: Option<String>,
+) -> (JoinHandle<()>, AcceptWaker) {
+    let waker = listener.waker();
+    let network = Arc::clone(network);
+    let thread = thread::spawn(move || {
+        while let Ok(stream) = get_stream(listener.as_ref()) {
+            if network.lock().map_err(|_| LockError).unwrap().is_closing() {
+                break;
+            }
+            attach_frontend(&network, stream, token.as_deref());
+        }
+    });
+    (thread, waker)
 }
@@ -1,35 +1,83 @@
+mod flood;
+mod frontend;
+mod names;
 mod pack_in;
 mod pack_out;
+mod reassembly;
+mod retransmission;
+mod sessions;
 mod topology;
 mod utils;
 
+pub use flood::{FloodConfig, FloodStats};
+pub use reassembly::ReassemblyStats;
+pub(crate) use retransmission::RETRANSMISSION_TICK;
+pub(crate) use sessions::SessionKind;
+
+use crate::network::flood::FloodScheduler;
+use crate::network::frontend::FrontendMirror;
+use crate::network::pack_in::{SeenSet, SEEN_RETENTION};
+use crate::network::reassembly::Reassembly;
+use crate::network::retransmission::RetransmissionTimer;
+use client_lib::communication::{Connection, MessageID};
+use client_lib::transport::Transport;
 use common_structs::leaf::LeafEvent;
 use common_structs::message::{Message, ServerType};
 use common_structs::types::Session;
 use crossbeam_channel::Sender;
 use petgraph::graphmap::DiGraphMap;
-use std::collections::HashMap;
-use std::net::TcpStream;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+use std::time::Instant;
 use wg_2024::network::NodeId;
-use wg_2024::packet::{Fragment, Packet};
+use wg_2024::packet::Packet;
 
 pub(super) struct Network {
     pub id: NodeId,
     pub packet_send: HashMap<NodeId, Sender<Packet>>,
     controller_send: Sender<LeafEvent>,
     topology: DiGraphMap<NodeId, i32>,
+    //HM<droneId, estimated drop rate>
+    drop_estimates: HashMap<NodeId, f64>,
     //HM<session, (serverId, OtherClientId, Waiting)>
     packs_waiting_for_ack: HashMap<u64, (NodeId, Option<NodeId>, Vec<Packet>)>,
+    //HM<session, timer>
+    retransmission_timers: HashMap<u64, RetransmissionTimer>,
     //HM<session, message>
     pub messages_waiting_for_ack: HashMap<u64, Message>,
+    //HM<session, (MessageId, kind)>
+    session_messages: HashMap<u64, (MessageID, SessionKind)>,
     //HM<serverId, (OtherClientId, Waiting)>
     queued_packs: HashMap<NodeId, (Option<NodeId>, Vec<Packet>)>,
     //HM<serverId (Path)>
     paths_to_leafs: HashMap<NodeId, Option<Vec<NodeId>>>,
     leaf_types: HashMap<NodeId, Option<ServerType>>,
-    partially_received: HashMap<u64, Vec<Option<Fragment>>>,
+    partially_received: Reassembly,
+    //(sourceId, session) of fully received messages
+    completed_sessions: SeenSet<(NodeId, Session)>,
+    //(serverId, OtherClientId, MessageId, payload hash) of forwarded message contents
+    seen_chat_messages: SeenSet<(NodeId, NodeId, MessageID, u64)>,
     current_session: Session,
-    pub frontend_stream: Option<TcpStream>,
+    flood_scheduler: FloodScheduler,
+    //time of the last clock tick
+    last_tick: Instant,
+    pub frontend_stream: Option<Connection>,
+    frontend_mirror: FrontendMirror,
+    //bumped every time a frontend attaches
+    frontend_generation: u64,
+    //the threads reading from the frontends, joined when the client is killed
+    frontend_threads: Vec<JoinHandle<()>>,
+    //the client was killed, frontends attaching now are turned away
+    closing: bool,
+    //a frontend still handshaking, closed if the client is killed meanwhile
+    handshaking: Option<Box<dyn Transport>>,
+    //the name peers are told, "client_<id>" until one is chosen
+    name: String,
+    //where what must survive a restart is kept, nothing is without it
+    data_dir: Option<PathBuf>,
+    //HM<serverId, OtherClientIds> of the chat servers we're registered to
+    chat_peers: BTreeMap<NodeId, BTreeSet<NodeId>>,
 }
 
 impl Network {
@@ -45,14 +93,33 @@ impl Network {
             packet_send,
             controller_send,
             topology,
+            drop_estimates: HashMap::new(),
             packs_waiting_for_ack: HashMap::new(),
+            retransmission_timers: HashMap::new(),
             messages_waiting_for_ack: Default::default(),
+            session_messages: HashMap::new(),
             queued_packs: HashMap::new(),
             paths_to_leafs: HashMap::new(),
             leaf_types: Default::default(),
-            partially_received: HashMap::new(),
+            partially_received: Reassembly::new(),
+            completed_sessions: SeenSet::new(SEEN_RETENTION),
+            seen_chat_messages: SeenSet::new(SEEN_RETENTION),
             current_session: 0,
+            flood_scheduler: FloodScheduler::new(),
+            last_tick: Instant::now(),
             frontend_stream: None,
+            frontend_mirror: FrontendMirror::default(),
+            frontend_generation: 0,
+            frontend_threads: Vec::new(),
+            closing: false,
+            handshaking: None,
+            name: format!("client_{}", id),
+            data_dir: None,
+            chat_peers: BTreeMap::new(),
         }
     }
+
+    pub fn reassembly_stats(&self) -> ReassemblyStats {
+        self.partially_received.stats()
+    }
 }
@@ -0,0 +1,219 @@
+use crate::network::pack_in::SeenSet;
+use crate::network::Network;
+use std::time::{Duration, Instant};
+
+//how many of the latest floods still accept responses
+const OUTSTANDING_FLOODS: usize = 8;
+
+#[derive(Debug, Copy, Clone)]
+pub struct FloodConfig {
+    //requests arriving this soon after a flood are served by it,
+    //cut down to the minimum interval if longer
+    pub window: Duration,
+    //minimum time between two floods, later requests are merged into one
+    pub min_interval: Duration,
+}
+
+impl Default for FloodConfig {
+    fn default() -> Self {
+        FloodConfig {
+            window: Duration::from_millis(200),
+            min_interval: Duration::from_millis(1000),
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FloodStats {
+    pub started: u64,
+    //requests that did not cause a flood of their own
+    pub suppressed: u64,
+    //responses to floods that are no longer outstanding
+    pub stale_responses: u64,
+}
+
+pub(super) struct FloodScheduler {
+    config: FloodConfig,
+    last_flood: Option<Instant>,
+    pending: bool,
+    outstanding: SeenSet<u64>,
+    next_flood_id: u64,
+    stats: FloodStats,
+}
+
+impl FloodScheduler {
+    pub fn new() -> Self {
+        FloodScheduler {
+            config: FloodConfig::default(),
+            last_flood: None,
+            pending: false,
+            outstanding: SeenSet::new(OUTSTANDING_FLOODS),
+            next_flood_id: 0,
+            stats: FloodStats::default(),
+        }
+    }
+
+    //a window longer than the interval would swallow the requests meant for the next flood
+    fn set_config(&mut self, config: FloodConfig) {
+        self.config = FloodConfig {
+            window: config.window.min(config.min_interval),
+            ..config
+        };
+    }
+
+    //true if a flood may start right away
+    fn request(&mut self, now: Instant) -> bool {
+        let Some(last_flood) = self.last_flood else {
+            return true;
+        };
+        if now.duration_since(last_flood) >= self.config.min_interval {
+            return true;
+        }
+        //covered by the flood in progress, or merged with the one already waiting
+        if now.duration_since(last_flood) < self.config.window || self.pending {
+            self.stats.suppressed += 1;
+        } else {
+            self.pending = true;
+        }
+        false
+    }
+
+    //true if a deferred flood can now start
+    fn due(&self, now: Instant) -> bool {
+        match self.last_flood {
+            Some(last_flood) if self.pending => {
+                now.duration_since(last_flood) >= self.config.min_interval
+            }
+            _ => false,
+        }
+    }
+
+    //registers a new flood and returns its id
+    fn start(&mut self, now: Instant) -> u64 {
+        let flood_id = self.next_flood_id;
+        self.next_flood_id += 1;
+        self.last_flood = Some(now);
+        self.pending = false;
+        self.outstanding.insert(flood_id);
+        self.stats.started += 1;
+        flood_id
+    }
+}
+
+impl Network {
+    //floods now if allowed, otherwise the request is merged into the next flood
+    pub fn request_flood(&mut self) {
+        if self.flood_scheduler.request(self.last_tick) {
+            self.initiate_flood();
+        }
+    }
+
+    //starts the deferred flood once the minimum interval has passed
+    pub(super) fn check_pending_flood(&mut self, now: Instant) {
+        if self.flood_scheduler.due(now) {
+            self.initiate_flood();
+        }
+    }
+
+    pub(super) fn next_flood_id(&mut self) -> u64 {
+        let now = self.last_tick;
+        self.flood_scheduler.start(now)
+    }
+
+    //false for responses to floods that are too old to be trusted
+    pub(super) fn is_flood_outstanding(&mut self, flood_id: u64) -> bool {
+        if self.flood_scheduler.outstanding.contains(&flood_id) {
+            return true;
+        }
+        self.flood_scheduler.stats.stale_responses += 1;
+        false
+    }
+
+    pub fn set_flood_config(&mut self, config: FloodConfig) {
+        self.flood_scheduler.set_config(config);
+    }
+
+    pub fn flood_stats(&self) -> FloodStats {
+        self.flood_scheduler.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+    use std::collections::HashMap;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn requests_in_the_window_are_served_by_the_last_flood() {
+        let start = Instant::now();
+        let mut scheduler = FloodScheduler::new();
+        assert!(scheduler.request(start));
+        scheduler.start(start);
+
+        assert!(!scheduler.request(start + ms(100)));
+        assert!(!scheduler.request(start + ms(199)));
+        assert_eq!(scheduler.stats.suppressed, 2);
+        //nothing left to do once the flood is done
+        assert!(!scheduler.due(start + ms(1_000)));
+    }
+
+    #[test]
+    fn later_requests_are_merged_into_one_flood_after_the_interval() {
+        let start = Instant::now();
+        let mut scheduler = FloodScheduler::new();
+        scheduler.request(start);
+        scheduler.start(start);
+
+        assert!(!scheduler.request(start + ms(200)));
+        assert!(!scheduler.request(start + ms(500)));
+        assert_eq!(scheduler.stats.suppressed, 1);
+        assert!(!scheduler.due(start + ms(999)));
+        assert!(scheduler.due(start + ms(1_000)));
+
+        scheduler.start(start + ms(1_000));
+        assert!(!scheduler.due(start + ms(2_000)));
+        assert_eq!(scheduler.stats.started, 2);
+        //the interval counts from the deferred flood
+        assert!(!scheduler.request(start + ms(1_999)));
+        assert!(scheduler.request(start + ms(2_999)));
+    }
+
+    #[test]
+    fn a_window_longer_than_the_interval_is_cut_down() {
+        let start = Instant::now();
+        let mut scheduler = FloodScheduler::new();
+        scheduler.set_config(FloodConfig {
+            window: ms(5_000),
+            min_interval: ms(1_000),
+        });
+        assert_eq!(scheduler.config.window, ms(1_000));
+
+        scheduler.start(start);
+        assert!(scheduler.request(start + ms(1_000)));
+    }
+
+    #[test]
+    fn only_the_latest_floods_accept_responses() {
+        let (controller_send, _) = unbounded();
+        let mut network = Network::new(1, HashMap::new(), controller_send);
+        let ids = (0..=OUTSTANDING_FLOODS)
+            .map(|_| network.next_flood_id())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, (0..=OUTSTANDING_FLOODS as u64).collect::<Vec<_>>());
+
+        assert!(!network.is_flood_outstanding(0));
+        for id in 1..=OUTSTANDING_FLOODS as u64 {
+            assert!(network.is_flood_outstanding(id));
+        }
+        assert!(!network.is_flood_outstanding(OUTSTANDING_FLOODS as u64 + 1));
+
+        let stats = network.flood_stats();
+        assert_eq!(stats.started, OUTSTANDING_FLOODS as u64 + 1);
+        assert_eq!(stats.stale_responses, 2);
+    }
+}
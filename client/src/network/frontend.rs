@@ -0,0 +1,278 @@
+use crate::network::Network;
+use client_lib::communication::TUICommand::{
+    DeleteMessage, Kill, UpdateChatRoom, UpdateMessageContent, UpdateMessageReaction,
+    UpdateMessageStatus, UpdateName, UpdatePeerLastSeen, UpdatePeerName, UpdatePeerStatus,
+};
+use client_lib::communication::{
+    ChatClientID, ChatServerID, Connection, MessageContent, MessageID, MessageStatus, Reaction,
+    TUICommand, TimeStamp,
+};
+use client_lib::transport::Transport;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::thread::JoinHandle;
+
+#[derive(Default)]
+struct MirroredMessage {
+    content: Option<MessageContent>,
+    //what it said before each edit, oldest first
+    history: Vec<MessageContent>,
+    //sent by us, the frontend learns it from the status coming first
+    own: bool,
+    //when it was first sent, edits keep it
+    sent: TimeStamp,
+    status: Option<MessageStatus>,
+    reaction: Option<Reaction>,
+    deleted: bool,
+}
+
+#[derive(Default)]
+struct MirroredPeer {
+    name: Option<String>,
+    reachable: Option<bool>,
+    last_seen: Option<TimeStamp>,
+}
+
+//everything the frontend is showing, replayed to every frontend that attaches
+#[derive(Default)]
+pub(super) struct FrontendMirror {
+    name: Option<String>,
+    //HM<serverId, (registered, reachable)>
+    rooms: BTreeMap<ChatServerID, (Option<bool>, Option<bool>)>,
+    //HM<(serverId, OtherClientId), peer>
+    peers: BTreeMap<(ChatServerID, ChatClientID), MirroredPeer>,
+    //HM<(serverId, OtherClientId, MessageId), message>
+    messages: HashMap<(ChatServerID, ChatClientID, MessageID), MirroredMessage>,
+    //messages in the order the frontend first saw them
+    message_order: Vec<(ChatServerID, ChatClientID, MessageID)>,
+}
+
+impl FrontendMirror {
+    pub fn apply(&mut self, command: &TUICommand) {
+        match command {
+            UpdateName(name) => self.name = Some(name.clone()),
+            UpdateChatRoom(server, registered, reachable) => {
+                let room = self.rooms.entry(*server).or_default();
+                room.0 = registered.or(room.0);
+                room.1 = reachable.or(room.1);
+            }
+            UpdatePeerName(server, peer, name) => {
+                let peer = self.peers.entry((*server, *peer)).or_default();
+                peer.name = name.clone().or(peer.name.take());
+            }
+            UpdatePeerStatus(server, peer, reachable) => {
+                if let Some(peer) = self.peers.get_mut(&(*server, *peer)) {
+                    peer.reachable = Some(*reachable);
+                }
+            }
+            UpdateMessageContent(server, peer, msg_id, content, time) => {
+                let message = self.message(*server, *peer, *msg_id);
+                //deleted messages stay deleted, as in the frontend
+                if message.deleted || message.content.as_ref() == Some(content) {
+                    return;
+                }
+                if message.content.is_none() {
+                    message.sent = *time;
+                }
+                if let Some(old) = message.content.replace(content.clone()) {
+                    message.history.push(old);
+                }
+            }
+            UpdateMessageStatus(server, peer, msg_id, status) => {
+                let message = self.message(*server, *peer, *msg_id);
+                if status.replaces(message.status) {
+                    message.status = Some(*status);
+                }
+            }
+            UpdateMessageReaction(server, peer, msg_id, reaction) => {
+                self.message(*server, *peer, *msg_id).reaction = *reaction;
+            }
+            DeleteMessage(server, peer, msg_id) => {
+                let message = self.message(*server, *peer, *msg_id);
+                *message = MirroredMessage {
+                    deleted: true,
+                    ..MirroredMessage::default()
+                };
+            }
+            UpdatePeerLastSeen(server, peer, time) => {
+                if let Some(peer) = self.peers.get_mut(&(*server, *peer)) {
+                    peer.last_seen = Some(*time);
+                }
+            }
+            Kill => {}
+        }
+    }
+
+    //records a change the frontend made on its own, its content makes a message ours
+    pub fn remember(&mut self, command: &TUICommand) {
+        if let UpdateMessageContent(server, peer, msg_id, _, _) = command {
+            self.message(*server, *peer, *msg_id).own = true;
+        }
+        self.apply(command);
+    }
+
+    pub fn is_own(&self, server: ChatServerID, peer: ChatClientID, msg_id: MessageID) -> bool {
+        self.messages
+            .get(&(server, peer, msg_id))
+            .is_some_and(|message| message.own)
+    }
+
+    fn message(
+        &mut self,
+        server: ChatServerID,
+        peer: ChatClientID,
+        msg_id: MessageID,
+    ) -> &mut MirroredMessage {
+        let key = (server, peer, msg_id);
+        if !self.messages.contains_key(&key) {
+            self.message_order.push(key);
+        }
+        self.messages.entry(key).or_default()
+    }
+
+    //the commands that bring a fresh frontend to the current state
+    pub fn replay(&self) -> Vec<TUICommand> {
+        let mut commands = Vec::new();
+        if let Some(name) = &self.name {
+            commands.push(UpdateName(name.clone()));
+        }
+        for (server, (registered, reachable)) in &self.rooms {
+            commands.push(UpdateChatRoom(*server, *registered, *reachable));
+        }
+        for ((server, id), peer) in &self.peers {
+            //the frontend ignores the name of a peer it does not know yet
+            commands.push(UpdatePeerName(*server, *id, None));
+            if peer.name.is_some() {
+                commands.push(UpdatePeerName(*server, *id, peer.name.clone()));
+            }
+            if let Some(reachable) = peer.reachable {
+                commands.push(UpdatePeerStatus(*server, *id, reachable));
+            }
+            if let Some(last_seen) = peer.last_seen {
+                commands.push(UpdatePeerLastSeen(*server, *id, last_seen));
+            }
+        }
+        for key in &self.message_order {
+            let (server, peer, msg_id) = *key;
+            let Some(message) = self.messages.get(key) else {
+                continue;
+            };
+            //a deleted message has nothing left to show
+            let (false, Some(content)) = (message.deleted, &message.content) else {
+                continue;
+            };
+            //a status coming first tells the frontend the message is ours, not a new one
+            let status = message
+                .status
+                .map(|status| UpdateMessageStatus(server, peer, msg_id, status));
+            if message.own {
+                commands.extend(status.clone());
+            }
+            //every version in order, so the frontend has the edit history again
+            for version in message.history.iter().chain([content]) {
+                commands.push(UpdateMessageContent(
+                    server,
+                    peer,
+                    msg_id,
+                    version.clone(),
+                    message.sent,
+                ));
+            }
+            if !message.own {
+                commands.extend(status);
+            }
+            if message.reaction.is_some() {
+                commands.push(UpdateMessageReaction(
+                    server,
+                    peer,
+                    msg_id,
+                    message.reaction,
+                ));
+            }
+        }
+        commands
+    }
+}
+
+impl Network {
+    //sends a command to the frontend, remembering it for the ones attaching later
+    pub fn notify_frontend(&mut self, command: TUICommand) {
+        self.frontend_mirror.apply(&command);
+        if let Some(stream) = &mut self.frontend_stream {
+            //the frontend is gone, wait for a new one
+            if stream.send(command).is_err() {
+                self.frontend_stream = None;
+            }
+        }
+    }
+
+    //records a change the frontend already made on its own
+    pub fn remember_frontend(&mut self, command: TUICommand) {
+        self.frontend_mirror.remember(&command);
+    }
+
+    //starts talking to a new frontend, replacing the old one, and returns its generation;
+    //none once the client is closing
+    pub fn attach_frontend(&mut self, mut stream: Connection) -> Option<u64> {
+        if self.closing {
+            stream.shutdown();
+            return None;
+        }
+        for command in self.frontend_mirror.replay() {
+            let _ = stream.send(command);
+        }
+        if let Some(old) = self.frontend_stream.replace(stream) {
+            old.shutdown();
+        }
+        self.frontend_generation += 1;
+        Some(self.frontend_generation)
+    }
+
+    //forgets the frontend, unless a newer one replaced it already
+    pub fn detach_frontend(&mut self, generation: u64) {
+        if generation == self.frontend_generation {
+            self.frontend_stream = None;
+        }
+    }
+
+    //keeps the thread reading from a frontend, forgetting the ones already done
+    pub fn add_frontend_thread(&mut self, thread: JoinHandle<()>) {
+        self.frontend_threads.retain(|t| !t.is_finished());
+        self.frontend_threads.push(thread);
+    }
+
+    //the client is killed: the frontend is told so and its stream closed,
+    //which ends the thread reading from it; a handshake under way is cut short
+    pub fn close_frontend(&mut self) {
+        self.notify_frontend(Kill);
+        self.closing = true;
+        if let Some(stream) = self.frontend_stream.take() {
+            stream.shutdown();
+        }
+        if let Some(stream) = self.handshaking.take() {
+            stream.close();
+        }
+    }
+
+    //keeps a handle to a frontend before its handshake, false once the client is closing
+    pub fn start_handshake(&mut self, stream: Box<dyn Transport>) -> bool {
+        if self.closing {
+            stream.close();
+            return false;
+        }
+        self.handshaking = Some(stream);
+        true
+    }
+
+    pub fn end_handshake(&mut self) {
+        self.handshaking = None;
+    }
+
+    pub fn is_closing(&self) -> bool {
+        self.closing
+    }
+
+    pub fn take_frontend_threads(&mut self) -> Vec<JoinHandle<()>> {
+        mem::take(&mut self.frontend_threads)
+    }
+}
@@ -0,0 +1,79 @@
+use crate::communication::CHAT_CODEC;
+use crate::network::Network;
+use client_lib::codec::Codec;
+use client_lib::communication::TUICommand::{UpdateName, UpdatePeerName};
+use common_structs::message::Message::ReqChatSend;
+use std::fs;
+use std::path::PathBuf;
+use wg_2024::network::NodeId;
+
+impl Network {
+    pub fn set_data_dir(&mut self, dir: PathBuf) {
+        self.data_dir = Some(dir);
+    }
+
+    //where the chosen name survives a restart, nowhere without a data dir
+    fn name_file(&self) -> Option<PathBuf> {
+        self.data_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("client_{}.name", self.id)))
+    }
+
+    //picks up the name saved by an earlier run, if any, and shows it
+    pub fn load_name(&mut self) {
+        let saved = self
+            .name_file()
+            .and_then(|path| fs::read_to_string(path).ok());
+        if let Some(name) = saved.filter(|name| !name.is_empty()) {
+            self.name = name;
+        }
+        self.notify_frontend(UpdateName(self.name.clone()));
+    }
+
+    //shows and saves the new name, then tells every known peer
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+        if let (Some(dir), Some(path)) = (&self.data_dir, self.name_file()) {
+            //a name that can't be saved is only lost at the next restart
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, &self.name));
+        }
+        self.notify_frontend(UpdateName(self.name.clone()));
+        let peers = self
+            .chat_peers
+            .iter()
+            .flat_map(|(server, peers)| peers.iter().map(move |peer| (*server, *peer)))
+            .collect::<Vec<_>>();
+        for (server, peer) in peers {
+            self.send_name(server, peer);
+        }
+    }
+
+    //the server accepted our registration, its clients are peers from now on
+    pub(super) fn add_chat_server(&mut self, server: NodeId) {
+        self.chat_peers.entry(server).or_default();
+    }
+
+    //the clients registered to the server, the ones we didn't know yet learn our name
+    pub(super) fn update_chat_peers(&mut self, server: NodeId, clients: &[NodeId]) {
+        let id = self.id;
+        let Some(known) = self.chat_peers.get_mut(&server) else {
+            return;
+        };
+        let new_peers = clients
+            .iter()
+            .copied()
+            .filter(|peer| *peer != id && known.insert(*peer))
+            .collect::<Vec<_>>();
+        for peer in new_peers {
+            self.send_name(server, peer);
+        }
+    }
+
+    fn send_name(&mut self, server: NodeId, peer: NodeId) {
+        let command = UpdatePeerName(server, self.id, Some(self.name.clone()));
+        if let Ok(chat_msg) = CHAT_CODEC.encode(&command) {
+            let message = ReqChatSend { to: peer, chat_msg };
+            self.send_message(message, server, None);
+        }
+    }
+}
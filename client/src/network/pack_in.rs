@@ -1,27 +1,69 @@
 use crate::communication::net::{new_ack, new_flood_resp};
-use crate::network::Network;
+use crate::communication::CHAT_CODEC;
+use crate::network::reassembly::Reassembled;
+use crate::network::{Network, SessionKind};
+use client_lib::codec::Codec;
 use client_lib::communication::TUICommand::{
     DeleteMessage, UpdateChatRoom, UpdateMessageContent, UpdateMessageReaction,
     UpdateMessageStatus, UpdatePeerLastSeen, UpdatePeerName,
 };
-use client_lib::communication::{send_message, MessageStatus, TUICommand};
+use client_lib::communication::{now, MessageID, MessageStatus, TUICommand};
 use common_structs::message::{Message, ServerType};
 use common_structs::types::{Routing, Session};
-use std::thread;
-use std::thread::sleep;
-use std::time::Duration;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use wg_2024::network::NodeId;
 use wg_2024::packet::NodeType::{Client, Server};
 use wg_2024::packet::{
     Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType,
 };
 
+//how many completed sessions and chat messages are remembered to spot duplicates
+pub(super) const SEEN_RETENTION: usize = 1024;
+
+//remembers the last `capacity` inserted values, forgetting the oldest first
+pub(super) struct SeenSet<T> {
+    order: VecDeque<T>,
+    seen: HashSet<T>,
+    capacity: usize,
+}
+
+impl<T: Copy + Eq + Hash> SeenSet<T> {
+    pub fn new(capacity: usize) -> Self {
+        SeenSet {
+            order: VecDeque::with_capacity(capacity),
+            seen: HashSet::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn contains(&self, value: &T) -> bool {
+        self.seen.contains(value)
+    }
+
+    //returns false if the value was already there
+    pub fn insert(&mut self, value: T) -> bool {
+        if !self.seen.insert(value) {
+            return false;
+        }
+        self.order.push_back(value);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
 impl Network {
     pub fn handle_packet(&mut self, packet: Packet) {
         let (routing, session, pack_type) =
             (packet.routing_header, packet.session_id, packet.pack_type);
         match pack_type {
             PacketType::MsgFragment(f) => self.handle_fragment_receive(routing, session, f),
-            PacketType::Ack(a) => self.handle_ack_receive(session, a),
+            PacketType::Ack(a) => self.handle_ack_receive(routing, session, a),
             PacketType::Nack(n) => self.handle_nack_receive(routing, session, n),
             PacketType::FloodRequest(f_req) => self.handle_flood_request_receive(session, f_req),
             PacketType::FloodResponse(f_res) => self.handle_flood_response_receive(f_res),
@@ -29,93 +71,112 @@ impl Network {
     }
     fn handle_fragment_receive(&mut self, routing: Routing, session: Session, fragment: Fragment) {
         //println!("storing: {:?}", fragment);
-        let mut partial_message = Vec::with_capacity(fragment.total_n_fragments as usize);
-
-        if let Some(stored_partial_message) = self.partially_received.remove(&session) {
-            partial_message = stored_partial_message;
-        } else {
-            for i in 0..fragment.total_n_fragments {
-                partial_message.push(None);
-            }
+        let source = routing.hops[0];
+        let fragment_index = fragment.fragment_index;
+        //a retransmission of an already completed message: only ack it again
+        if self.completed_sessions.contains(&(source, session)) {
+            return self.send_ack(routing, session, fragment_index);
         }
-        partial_message[fragment.fragment_index as usize] = Some(fragment.clone());
-
-        let mut complete = true;
-        for frag in &partial_message {
-            if frag.is_none() {
-                complete = false;
+        match self
+            .partially_received
+            .insert(source, session, fragment, self.last_tick)
+        {
+            Reassembled::Complete(fragments) => {
+                //println!("message complete");
+                self.completed_sessions.insert((source, session));
+                let message = Message::from_fragments(fragments);
+                //println!("completed message: {:?}", message.clone());
+                //if message makes sense
+                if let Ok(message) = message {
+                    self.handle_message_receive(source, message);
+                }
             }
+            Reassembled::Pending => {}
+            //not acked: the sender keeps retrying until its session fails
+            Reassembled::Invalid => return,
         }
-
-        if complete {
-            //println!("message complete");
-            let mut message = Vec::new();
-            for frag in partial_message.iter().flatten() {
-                message.push(frag.clone());
-            }
-            let message = Message::from_fragments(message);
-            //println!("completed message: {:?}", message.clone());
-            //if message makes sense
-            if let Ok(message) = message {
-                match message {
-                    Message::RespServerType(server_type) => {
-                        let server = routing.hops[0];
-                        self.leaf_types.insert(server, Some(server_type.clone()));
-                        if server_type == ServerType::Chat {
-                            self.check_queued(server);
-                            if let Some(stream) = &mut self.frontend_stream {
-                                let _ = send_message(
-                                    stream,
-                                    UpdateChatRoom(server, Some(false), Some(true)),
-                                );
-                            }
-                        }
+        self.send_ack(routing, session, fragment_index);
+    }
+    fn send_ack(&mut self, routing: Routing, session: Session, fragment_index: u64) {
+        let ack = new_ack(routing, session, fragment_index);
+        //the fragment may have come over a link removed since: the sender will resend it
+        let Some(sender) = self.packet_send.get(&ack.routing_header.hops[1]).cloned() else {
+            return;
+        };
+        self.send_packet(ack, &sender, None);
+    }
+    fn handle_message_receive(&mut self, server: NodeId, message: Message) {
+        match message {
+            Message::RespServerType(server_type) => {
+                self.leaf_types.insert(server, Some(server_type.clone()));
+                if server_type == ServerType::Chat {
+                    self.check_queued(server);
+                    self.notify_frontend(UpdateChatRoom(server, Some(false), Some(true)));
+                }
+            }
+            Message::RespClientList(peers) => {
+                for peer in &peers {
+                    if *peer != self.id {
+                        self.notify_frontend(UpdatePeerName(server, *peer, None));
                     }
-                    Message::RespClientList(peers) => {
-                        let server = routing.hops[0];
-                        for peer in peers {
-                            if peer != self.id {
-                                if let Some(ref mut stream) = self.frontend_stream {
-                                    let _ =
-                                        send_message(stream, UpdatePeerName(server, peer, None));
-                                }
-                            }
-                        }
+                }
+                self.update_chat_peers(server, &peers);
+            }
+            Message::RespChatFrom { from, chat_msg } => {
+                let content: Result<TUICommand, _> = CHAT_CODEC.decode(&chat_msg);
+                //the server may relay a resent message under a new session: a message must
+                //not show up twice, the other commands may be repeated on purpose,
+                //e.g. a name or a reaction set back to an earlier one.
+                //edits reuse the id of the message, the payload tells them apart
+                if let Ok(UpdateMessageContent(_, _, msg_id, _, _)) = content {
+                    let mut hasher = DefaultHasher::new();
+                    chat_msg.hash(&mut hasher);
+                    let fingerprint = (server, from, msg_id, hasher.finish());
+                    if !self.seen_chat_messages.insert(fingerprint) {
+                        return;
                     }
-                    Message::RespChatFrom { chat_msg, .. } => {
-                        if let Some(ref mut stream) = self.frontend_stream {
-                            let content: Result<TUICommand, _> = serde_json::from_slice(&chat_msg);
-                            if let Ok(content) = content {
-                                match content {
-                                    UpdatePeerName(_, _, _) //after SetName
-                                    | UpdatePeerLastSeen(_, _) //when interacting with room
-                                    | UpdateMessageStatus(_, _, _, _) //DONE: after read message or received
-                                    | UpdateMessageReaction(_, _, _, _)
-                                    | DeleteMessage(_, _, _) => { //DONE: after DeleteMessage
-                                        let _ = send_message(stream, content);
-                                    }
-                                    UpdateMessageContent(_, _, _, _) => {
-                                        let _ = send_message(stream, content);
-                                        //send received to peer
-                                    }//DONE: after SendMessage
-                                    _ => {}
-                                }
-                            }
+                }
+                if let Ok(content) = content {
+                    match content {
+                        UpdatePeerName(_, _, _) //after SetName
+                        | UpdatePeerLastSeen(_, _, _) //when interacting with room
+                        | UpdateMessageStatus(_, _, _, _) //DONE: after read message or received
+                        | UpdateMessageReaction(_, _, _, _)
+                        | DeleteMessage(_, _, _) => { //DONE: after DeleteMessage
+                            self.notify_frontend(content);
                         }
+                        UpdateMessageContent(_, _, msg_id, _, _) => {
+                            //peers only edit their own messages
+                            if self.frontend_mirror.is_own(server, from, msg_id) {
+                                return;
+                            }
+                            self.notify_frontend(content);
+                            self.send_receipt(server, from, msg_id);
+                        }//DONE: after SendMessage
+                        _ => {}
                     }
-                    Message::ErrUnsupportedRequestType | Message::ErrNotExistentClient => {}
-                    _ => {}
+                    //anything the peer sends shows it is around, by our clock
+                    self.notify_frontend(UpdatePeerLastSeen(server, from, now()));
                 }
             }
-        } else {
-            self.partially_received.insert(session, partial_message);
+            Message::ErrUnsupportedRequestType | Message::ErrNotExistentClient => {}
+            _ => {}
         }
-        let ack = new_ack(routing, session, fragment.fragment_index);
-        let sender = ack.routing_header.hops[1];
-        let sender = self.packet_send.get(&sender).unwrap().clone();
-        self.send_packet(ack, &sender, None);
     }
-    fn handle_ack_receive(&mut self, session: Session, ack: Ack) {
+    //tells the sender its message made it here, whether or not anyone reads it
+    fn send_receipt(&mut self, server: NodeId, peer: NodeId, msg_id: MessageID) {
+        let command = UpdateMessageStatus(server, self.id, msg_id, MessageStatus::ReceivedByPeer);
+        if let Ok(chat_msg) = CHAT_CODEC.encode(&command) {
+            let message = Message::ReqChatSend { to: peer, chat_msg };
+            self.send_message(message, server, Some((msg_id, SessionKind::Receipt)));
+        }
+    }
+    fn handle_ack_receive(&mut self, routing: Routing, session: Session, ack: Ack) {
+        //every drone between the leaf and us forwarded the fragment and its ack
+        if routing.hops.len() > 2 {
+            self.record_delivery(&routing.hops[1..routing.hops.len() - 1]);
+        }
+
         let waiting_for_ack_session = self.packs_waiting_for_ack.remove(&session);
         if let Some(mut waiting_for_ack_session) = waiting_for_ack_session {
             let mut remove = None;
@@ -136,25 +197,23 @@ impl Network {
                     session,
                     message.clone()
                 );*/
+                let chat_message = self.session_messages.remove(&session);
                 let server = waiting_for_ack_session.0;
                 let recipient = waiting_for_ack_session.1;
-                if let Some(ref mut stream) = &mut self.frontend_stream {
-                    if let Some(recipient) = recipient {
-                        let _ = send_message(
-                            stream,
-                            UpdateMessageStatus(
-                                server,
-                                recipient,
-                                session,
-                                MessageStatus::ReceivedByServer,
-                            ),
-                        );
-                    }
-                    if let Some(Message::ReqChatRegistration) = message {
-                        let _ =
-                            send_message(stream, UpdateChatRoom(server, Some(true), Some(true)));
-                        self.send_message(Message::ReqChatClients, server, None);
-                    }
+                if let (Some(recipient), Some((msg_id, SessionKind::Content))) =
+                    (recipient, chat_message)
+                {
+                    self.notify_frontend(UpdateMessageStatus(
+                        server,
+                        recipient,
+                        msg_id,
+                        MessageStatus::ReceivedByServer,
+                    ));
+                }
+                if let Some(Message::ReqChatRegistration) = message {
+                    self.add_chat_server(server);
+                    self.notify_frontend(UpdateChatRoom(server, Some(true), Some(true)));
+                    self.send_message(Message::ReqChatClients, server, None);
                 }
             } else {
                 self.packs_waiting_for_ack
@@ -163,37 +222,55 @@ impl Network {
         }
     }
     fn handle_nack_receive(&mut self, routing: Routing, session: Session, nack: Nack) {
-        if let NackType::ErrorInRouting(node_id) = nack.nack_type {
-            self.topology.remove_node(node_id);
-            self.update_reachable_paths();
+        //the nack starts from the drone that reported the problem
+        let reporter = routing.hops[0];
+        match nack.nack_type {
+            NackType::ErrorInRouting(node_id) => {
+                //only the link between the reporter and the next hop is known to be down
+                self.topology.remove_edge(reporter, node_id);
+                self.topology.remove_edge(node_id, reporter);
+                self.update_reachable_paths();
+            }
+            NackType::Dropped => {
+                self.record_drop(reporter);
+            }
+            _ => {}
         }
 
-        let packets_waiting_for_session = self.packs_waiting_for_ack.get(&session);
-        let leaf = routing.hops[0];
-        if let Some(packs) = packets_waiting_for_session {
-            let pack = packs.2.iter().find(|p| {
+        //take the nacked fragment out of the ones waiting for an ack
+        let mut requeue = None;
+        if let Some(packs) = self.packs_waiting_for_ack.get_mut(&session) {
+            let position = packs.2.iter().position(|p| {
                 if let PacketType::MsgFragment(f) = &p.pack_type {
                     return f.fragment_index == nack.fragment_index;
                 }
                 false
             });
-            if let Some(pack) = pack {
-                //requeue packet
-                let queue_data = self.queued_packs.remove(&leaf);
-                if let Some(queue_data) = queue_data {
-                    let mut queue = queue_data.1;
-                    queue.push(pack.clone());
-                    self.queued_packs.insert(leaf, (packs.1, queue));
-                } else {
-                    self.queued_packs
-                        .insert(leaf, (packs.1, vec![pack.clone()]));
-                }
+            if let Some(position) = position {
+                requeue = Some((packs.0, packs.1, packs.2.remove(position)));
+            }
+            if packs.2.is_empty() {
+                self.packs_waiting_for_ack.remove(&session);
+            }
+        }
+        if let Some((leaf, recipient, pack)) = requeue {
+            //requeue packet
+            let queue_data = self.queued_packs.remove(&leaf);
+            if let Some(queue_data) = queue_data {
+                let mut queue = queue_data.1;
+                queue.push(pack);
+                self.queued_packs.insert(leaf, (recipient, queue));
+            } else {
+                self.queued_packs.insert(leaf, (recipient, vec![pack]));
+            }
 
-                //recheck queue for leaf
+            //resend right away if there's still a route, otherwise wait for the flood
+            if let Some(Some(_)) = self.paths_to_leafs.get(&leaf) {
                 self.check_queued(leaf);
+                return;
             }
         }
-        self.initiate_flood();
+        self.request_flood();
     }
     fn handle_flood_request_receive(&mut self, session: Session, flood_request: FloodRequest) {
         let flood_res = new_flood_resp(self.id, Client, session, flood_request);
@@ -202,6 +279,10 @@ impl Network {
         self.send_packet(flood_res, &sender, None);
     }
     fn handle_flood_response_receive(&mut self, flood_response: FloodResponse) {
+        //paths from old floods may go through nodes removed since
+        if !self.is_flood_outstanding(flood_response.flood_id) {
+            return;
+        }
         let last = flood_response.path_trace.last().copied();
         let mut try_server_type_discovery = false;
         if let Some((id, node_type)) = last {
@@ -239,3 +320,115 @@ impl Network {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_lib::communication::MessageContent::TextMessage;
+    use client_lib::communication::Reaction::Heart;
+    use crossbeam_channel::unbounded;
+    use std::collections::HashMap;
+    use wg_2024::network::SourceRoutingHeader;
+
+    const SERVER: NodeId = 20;
+    const PEER: NodeId = 2;
+
+    fn network() -> Network {
+        let (controller_send, _) = unbounded();
+        Network::new(1, HashMap::new(), controller_send)
+    }
+
+    //what the server hands over from PEER
+    fn relay(network: &mut Network, command: &TUICommand) {
+        let chat_msg = CHAT_CODEC.encode(command).unwrap();
+        network.handle_message_receive(
+            SERVER,
+            Message::RespChatFrom {
+                from: PEER,
+                chat_msg,
+            },
+        );
+    }
+
+    fn text(msg_id: MessageID, text: &str, time: u64) -> TUICommand {
+        UpdateMessageContent(SERVER, PEER, msg_id, TextMessage(text.to_string()), time)
+    }
+
+    //every content shown to the frontend is answered with a receipt
+    fn receipts(network: &Network) -> usize {
+        network
+            .session_messages
+            .values()
+            .filter(|(_, kind)| *kind == SessionKind::Receipt)
+            .count()
+    }
+
+    #[test]
+    fn a_relayed_message_shows_up_once_and_its_edits_get_through() {
+        let mut network = network();
+        relay(&mut network, &text(7, "hello", 100));
+        relay(&mut network, &text(7, "hello", 100));
+        assert_eq!(receipts(&network), 1);
+
+        relay(&mut network, &text(8, "hello", 100));
+        assert_eq!(receipts(&network), 2);
+
+        //an edit, then one back to the first text
+        relay(&mut network, &text(7, "edited", 100));
+        relay(&mut network, &text(7, "hello", 101));
+        assert_eq!(receipts(&network), 4);
+    }
+
+    #[test]
+    fn a_peer_cannot_edit_our_messages() {
+        let mut network = network();
+        network.remember_frontend(text(7, "mine", 100));
+        relay(&mut network, &text(7, "not mine", 101));
+        assert_eq!(receipts(&network), 0);
+
+        let replay = network.frontend_mirror.replay();
+        assert!(!replay.contains(&text(7, "not mine", 100)), "{:?}", replay);
+    }
+
+    #[test]
+    fn repeated_commands_are_not_taken_for_duplicates() {
+        let mut network = network();
+        relay(&mut network, &text(7, "hello", 100));
+        let reaction = |reaction| UpdateMessageReaction(SERVER, PEER, 7, reaction);
+        relay(&mut network, &reaction(Some(Heart)));
+        relay(&mut network, &reaction(None));
+        relay(&mut network, &reaction(Some(Heart)));
+
+        let replay = network.frontend_mirror.replay();
+        assert!(replay.contains(&reaction(Some(Heart))), "{:?}", replay);
+    }
+
+    #[test]
+    fn a_drone_losing_one_link_stays_usable() {
+        let (packet_send, packet_recv) = unbounded();
+        let (controller_send, _) = unbounded();
+        let mut network = Network::new(1, HashMap::from([(11, packet_send)]), controller_send);
+        network.add_path(&[1, 11, SERVER], true).unwrap();
+        network.add_path(&[1, 11, 12, SERVER], true).unwrap();
+        network.paths_to_leafs.insert(SERVER, None);
+        network.update_unreachable_paths();
+        assert_eq!(network.paths_to_leafs[&SERVER], Some(vec![1, 11, SERVER]));
+
+        network.send_message(Message::ReqChatClients, SERVER, None);
+        assert_eq!(packet_recv.try_iter().count(), 1);
+
+        //11 cannot reach the server anymore, it can still reach 12
+        let nack = Nack {
+            fragment_index: 0,
+            nack_type: NackType::ErrorInRouting(SERVER),
+        };
+        let routing = SourceRoutingHeader::new(vec![11, 1], 1);
+        network.handle_packet(Packet::new_nack(routing, 0, nack));
+
+        let route = vec![1, 11, 12, SERVER];
+        assert_eq!(network.paths_to_leafs[&SERVER], Some(route.clone()));
+        let resent = packet_recv.try_iter().collect::<Vec<_>>();
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0].routing_header.hops, route);
+    }
+}
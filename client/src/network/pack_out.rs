@@ -1,23 +1,22 @@
-use crate::network::Network;
+use crate::network::{Network, SessionKind};
+use client_lib::communication::MessageID;
 use common_structs::leaf::LeafEvent::PacketSend;
 use common_structs::message::Message;
-use common_structs::types::{Routing, Session};
+use common_structs::types::Routing;
 use crossbeam_channel::Sender;
 use wg_2024::network::NodeId;
 use wg_2024::packet::{FloodRequest, NodeType, Packet, PacketType};
 
 impl Network {
-    pub fn initiate_flood(&mut self) {
+    pub(super) fn initiate_flood(&mut self) {
         //construct flood request packet
         let mut routing = Routing::empty_route();
         routing.hop_index = 1;
         let packet = Packet::new_flood_request(
             routing,
             0,
-            FloodRequest::initialize(self.current_session, self.id, NodeType::Client),
+            FloodRequest::initialize(self.next_flood_id(), self.id, NodeType::Client),
         );
-        //increment session
-        self.current_session += 1;
         //for every neighbor
         let mut senders = Vec::new();
         for sender in self.packet_send.values() {
@@ -29,16 +28,21 @@ impl Network {
             self.send_packet(packet.clone(), &sender, None);
         }
     }
-    pub fn send_message(&mut self, message: Message, target: NodeId, session: Option<Session>) {
-        // if is ReqChatSend, it's from the TUI. handle case separately.
-        let session = if let Some(session) = session {
-            session
-        } else {
-            self.current_session
-        };
+    pub fn send_message(
+        &mut self,
+        message: Message,
+        target: NodeId,
+        chat_message: Option<(MessageID, SessionKind)>,
+    ) {
+        let session = self.next_session();
+        // if is ReqChatSend, it's from the TUI: remember which chat message it's about
+        if let Some(chat_message) = chat_message {
+            self.session_messages.insert(session, chat_message);
+        }
         //starts sending message //TODO: adjust common
         self.messages_waiting_for_ack
             .insert(session, message.clone());
+        self.start_retransmission_timer(session, target);
         // if ReqChatSend set recipient to some(ClientId)
         let recipient = match message {
             Message::ReqChatSend { to: recipient, .. } => Some(recipient),
@@ -78,10 +82,8 @@ impl Network {
                 "sending later: non existing route to {}, {:?}",
                 target, message
             );*/
-            self.initiate_flood();
+            self.request_flood();
         }
-
-        self.current_session += 1;
     }
 
     pub fn send_packet(
@@ -0,0 +1,288 @@
+use common_structs::types::Session;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+use wg_2024::network::NodeId;
+use wg_2024::packet::Fragment;
+
+//sessions not completed in this time are discarded
+const MAX_SESSION_AGE: Duration = Duration::from_secs(30);
+//memory that incomplete sessions may hold at once
+const MAX_BUFFERED_BYTES: usize = 16 * 1024 * 1024;
+
+struct PartialMessage {
+    fragments: Vec<Option<Fragment>>,
+    missing: usize,
+    started: Instant,
+}
+
+impl PartialMessage {
+    fn bytes(&self) -> usize {
+        self.fragments.len() * size_of::<Option<Fragment>>()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ReassemblyStats {
+    //sessions dropped because they were too old
+    pub expired: u64,
+    //sessions dropped to stay under the memory cap
+    pub evicted: u64,
+    //sessions replaced by a fragment with a different total
+    pub stale: u64,
+    //fragments refused because they could never be part of a message,
+    //whatever was buffered for their session is dropped with them
+    pub invalid: u64,
+}
+
+pub(super) enum Reassembled {
+    Complete(Vec<Fragment>),
+    Pending,
+    //the fragment can never be part of a message, its session was dropped
+    Invalid,
+}
+
+//fragments of incomplete messages, keyed by (source, session)
+pub(super) struct Reassembly {
+    sessions: HashMap<(NodeId, Session), PartialMessage>,
+    buffered_bytes: usize,
+    max_age: Duration,
+    max_buffered_bytes: usize,
+    stats: ReassemblyStats,
+}
+
+impl Reassembly {
+    pub fn new() -> Self {
+        Reassembly {
+            sessions: HashMap::new(),
+            buffered_bytes: 0,
+            max_age: MAX_SESSION_AGE,
+            max_buffered_bytes: MAX_BUFFERED_BYTES,
+            stats: ReassemblyStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> ReassemblyStats {
+        self.stats
+    }
+
+    //stores a fragment, returning all of them once its message is complete
+    pub fn insert(
+        &mut self,
+        source: NodeId,
+        session: Session,
+        fragment: Fragment,
+        now: Instant,
+    ) -> Reassembled {
+        let total = fragment.total_n_fragments as usize;
+        let index = fragment.fragment_index as usize;
+        let needed_bytes = total.saturating_mul(size_of::<Option<Fragment>>());
+        if total == 0 || index >= total || needed_bytes > self.max_buffered_bytes {
+            self.discard(&(source, session));
+            self.stats.invalid += 1;
+            return Reassembled::Invalid;
+        }
+
+        let key = (source, session);
+        //a different total means the id was reused: the old fragments are stale
+        if let Some(partial) = self.sessions.get(&key) {
+            if partial.fragments.len() != total {
+                self.discard(&key);
+                self.stats.stale += 1;
+            }
+        }
+
+        if !self.sessions.contains_key(&key) {
+            self.make_room(needed_bytes);
+            self.buffered_bytes += needed_bytes;
+            self.sessions.insert(
+                key,
+                PartialMessage {
+                    fragments: vec![None; total],
+                    missing: total,
+                    started: now,
+                },
+            );
+        }
+
+        let Some(partial) = self.sessions.get_mut(&key) else {
+            return Reassembled::Pending;
+        };
+        if partial.fragments[index].is_none() {
+            partial.fragments[index] = Some(fragment);
+            partial.missing -= 1;
+        }
+        if partial.missing > 0 {
+            return Reassembled::Pending;
+        }
+
+        match self.discard(&key) {
+            Some(partial) => {
+                Reassembled::Complete(partial.fragments.into_iter().flatten().collect())
+            }
+            None => Reassembled::Pending,
+        }
+    }
+
+    //drops every session older than the max age
+    pub fn evict_expired(&mut self, now: Instant) {
+        let expired = self
+            .sessions
+            .iter()
+            .filter(|(_, partial)| now.duration_since(partial.started) > self.max_age)
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        for key in expired {
+            self.discard(&key);
+            self.stats.expired += 1;
+        }
+    }
+
+    //drops the oldest sessions until the new one fits under the cap
+    fn make_room(&mut self, needed_bytes: usize) {
+        while self.buffered_bytes + needed_bytes > self.max_buffered_bytes {
+            let oldest = self
+                .sessions
+                .iter()
+                .min_by_key(|(_, partial)| partial.started)
+                .map(|(key, _)| *key);
+            let Some(oldest) = oldest else {
+                return;
+            };
+            self.discard(&oldest);
+            self.stats.evicted += 1;
+        }
+    }
+
+    fn discard(&mut self, key: &(NodeId, Session)) -> Option<PartialMessage> {
+        let partial = self.sessions.remove(key)?;
+        self.buffered_bytes -= partial.bytes();
+        Some(partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wg_2024::packet::FRAGMENT_DSIZE;
+
+    const SOURCE: NodeId = 20;
+    const SLOT: usize = size_of::<Option<Fragment>>();
+
+    fn fragment(index: u64, total: u64) -> Fragment {
+        Fragment::new(index, total, [index as u8; FRAGMENT_DSIZE])
+    }
+
+    fn is_complete(reassembled: Reassembled, fragments: usize) -> bool {
+        matches!(reassembled, Reassembled::Complete(f) if f.len() == fragments)
+    }
+
+    #[test]
+    fn fragments_are_put_back_in_order() {
+        let now = Instant::now();
+        let mut reassembly = Reassembly::new();
+        assert!(matches!(
+            reassembly.insert(SOURCE, 0, fragment(1, 2), now),
+            Reassembled::Pending
+        ));
+        let Reassembled::Complete(fragments) = reassembly.insert(SOURCE, 0, fragment(0, 2), now)
+        else {
+            panic!("the message should be complete");
+        };
+        let indexes = fragments
+            .iter()
+            .map(|f| f.fragment_index)
+            .collect::<Vec<_>>();
+        assert_eq!(indexes, vec![0, 1]);
+        assert_eq!(reassembly.buffered_bytes, 0);
+    }
+
+    #[test]
+    fn sessions_older_than_the_max_age_are_dropped() {
+        let start = Instant::now();
+        let mut reassembly = Reassembly::new();
+        reassembly.insert(SOURCE, 0, fragment(0, 2), start);
+
+        reassembly.evict_expired(start + MAX_SESSION_AGE);
+        assert_eq!(reassembly.stats().expired, 0);
+        reassembly.evict_expired(start + MAX_SESSION_AGE + Duration::from_millis(1));
+        assert_eq!(reassembly.stats().expired, 1);
+        assert_eq!(reassembly.buffered_bytes, 0);
+
+        //the first fragment is gone, the late one starts over
+        let late = reassembly.insert(SOURCE, 0, fragment(1, 2), start + MAX_SESSION_AGE);
+        assert!(matches!(late, Reassembled::Pending));
+    }
+
+    #[test]
+    fn the_oldest_sessions_make_room_for_new_ones() {
+        let start = Instant::now();
+        let mut reassembly = Reassembly::new();
+        reassembly.max_buffered_bytes = 3 * SLOT;
+
+        reassembly.insert(SOURCE, 0, fragment(0, 2), start);
+        reassembly.insert(SOURCE, 1, fragment(0, 2), start + Duration::from_secs(1));
+        assert_eq!(reassembly.stats().evicted, 1);
+        assert_eq!(reassembly.buffered_bytes, 2 * SLOT);
+
+        let kept = reassembly.insert(SOURCE, 1, fragment(1, 2), start);
+        assert!(is_complete(kept, 2));
+        let evicted = reassembly.insert(SOURCE, 0, fragment(1, 2), start);
+        assert!(matches!(evicted, Reassembled::Pending));
+    }
+
+    #[test]
+    fn a_message_larger_than_the_cap_is_refused() {
+        let mut reassembly = Reassembly::new();
+        reassembly.max_buffered_bytes = 3 * SLOT;
+
+        let refused = reassembly.insert(SOURCE, 0, fragment(0, 4), Instant::now());
+        assert!(matches!(refused, Reassembled::Invalid));
+        assert_eq!(reassembly.stats().invalid, 1);
+        assert_eq!(reassembly.buffered_bytes, 0);
+    }
+
+    #[test]
+    fn an_index_out_of_range_drops_the_session() {
+        let now = Instant::now();
+        let mut reassembly = Reassembly::new();
+        reassembly.insert(SOURCE, 0, fragment(0, 3), now);
+
+        let invalid = reassembly.insert(SOURCE, 0, fragment(3, 3), now);
+        assert!(matches!(invalid, Reassembled::Invalid));
+        assert_eq!(reassembly.stats().invalid, 1);
+        assert_eq!(reassembly.buffered_bytes, 0);
+
+        //what was buffered went with it: the message is not completed from the rest
+        reassembly.insert(SOURCE, 0, fragment(1, 3), now);
+        let rest = reassembly.insert(SOURCE, 0, fragment(2, 3), now);
+        assert!(matches!(rest, Reassembled::Pending));
+    }
+
+    #[test]
+    fn a_different_total_replaces_the_stale_session() {
+        let now = Instant::now();
+        let mut reassembly = Reassembly::new();
+        reassembly.insert(SOURCE, 0, fragment(0, 3), now);
+        reassembly.insert(SOURCE, 0, fragment(1, 3), now);
+
+        let replaced = reassembly.insert(SOURCE, 0, fragment(1, 2), now);
+        assert!(matches!(replaced, Reassembled::Pending));
+        assert_eq!(reassembly.stats().stale, 1);
+        assert_eq!(reassembly.buffered_bytes, 2 * SLOT);
+
+        let complete = reassembly.insert(SOURCE, 0, fragment(0, 2), now);
+        assert!(is_complete(complete, 2));
+    }
+
+    #[test]
+    fn sessions_are_kept_apart_by_source() {
+        let now = Instant::now();
+        let mut reassembly = Reassembly::new();
+        reassembly.insert(SOURCE, 0, fragment(0, 2), now);
+        let other = reassembly.insert(SOURCE + 1, 0, fragment(1, 2), now);
+        assert!(matches!(other, Reassembled::Pending));
+        let complete = reassembly.insert(SOURCE, 0, fragment(1, 2), now);
+        assert!(is_complete(complete, 2));
+    }
+}
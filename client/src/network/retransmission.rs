@@ -0,0 +1,279 @@
+use crate::network::{Network, SessionKind};
+use client_lib::communication::MessageStatus::FailedToSend;
+use client_lib::communication::TUICommand::UpdateMessageStatus;
+use common_structs::message::Message;
+use common_structs::types::Session;
+use std::time::{Duration, Instant};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+
+//how often the client checks for expired sessions
+pub(crate) const RETRANSMISSION_TICK: Duration = Duration::from_millis(100);
+//time to wait for the first ack, doubled at every retransmission
+const BASE_TIMEOUT: Duration = Duration::from_millis(500);
+//retransmissions tried before the session is marked as failed
+const MAX_ATTEMPTS: u32 = 5;
+
+pub(super) struct RetransmissionTimer {
+    deadline: Instant,
+    attempts: u32,
+    //where the session is headed, its fragments may all be queued
+    leaf: NodeId,
+}
+
+impl Network {
+    //starts timing a session as it is handed to the network, by the clock of the last tick
+    pub(super) fn start_retransmission_timer(&mut self, session: Session, leaf: NodeId) {
+        let timer = RetransmissionTimer {
+            deadline: self.last_tick + BASE_TIMEOUT,
+            attempts: 0,
+            leaf,
+        };
+        self.retransmission_timers.insert(session, timer);
+    }
+
+    //checks every session still waiting for acks against its deadline
+    pub fn handle_tick(&mut self, now: Instant) {
+        self.last_tick = now;
+        self.partially_received.evict_expired(now);
+        self.check_pending_flood(now);
+
+        //forget sessions that got fully acked or were given up on,
+        //the ones with fragments still queued keep their timer
+        self.retransmission_timers
+            .retain(|session, _| self.messages_waiting_for_ack.contains_key(session));
+
+        let expired = self
+            .retransmission_timers
+            .iter()
+            .filter(|(_, timer)| timer.deadline <= now)
+            .map(|(session, _)| *session)
+            .collect::<Vec<_>>();
+        for session in expired {
+            self.retransmit_session(session, now);
+        }
+    }
+
+    fn retransmit_session(&mut self, session: Session, now: Instant) {
+        let Some(timer) = self.retransmission_timers.get_mut(&session) else {
+            return;
+        };
+        let leaf = timer.leaf;
+        timer.attempts += 1;
+        if timer.attempts > MAX_ATTEMPTS {
+            self.retransmission_timers.remove(&session);
+            self.fail_session(session, leaf);
+            return;
+        }
+        //exponential backoff
+        timer.deadline = now + BASE_TIMEOUT * 2u32.pow(timer.attempts);
+
+        let route = self.paths_to_leafs.get(&leaf).cloned().flatten();
+        let waiting = self.packs_waiting_for_ack.remove(&session);
+        match route {
+            None => self.request_flood(),
+            //fragments nacked or never sent wait in the queue for a route
+            Some(_) if self.queued_packs.contains_key(&leaf) => self.check_queued(leaf),
+            Some(_) => {}
+        }
+        let Some((leaf, recipient, packs)) = waiting else {
+            return;
+        };
+        //resend every fragment still missing an ack, on the best known route
+        let mut unsent = Vec::new();
+        for mut pack in packs {
+            if let Some(route) = &route {
+                pack.routing_header = SourceRoutingHeader::new(route.clone(), 1);
+            }
+            let sender = pack
+                .routing_header
+                .hops
+                .get(1)
+                .and_then(|first_hop| self.packet_send.get(first_hop))
+                .cloned();
+            match sender {
+                Some(sender) => self.send_packet(pack, &sender, recipient),
+                None => unsent.push(pack),
+            }
+        }
+
+        //keep the ones that could not leave so the next deadline retries them
+        if !unsent.is_empty() {
+            match self.packs_waiting_for_ack.get_mut(&session) {
+                Some(waiting) => waiting.2.extend(unsent),
+                None => {
+                    self.packs_waiting_for_ack
+                        .insert(session, (leaf, recipient, unsent));
+                }
+            }
+        }
+    }
+
+    //gives up on a session and tells the frontend the message was not delivered
+    fn fail_session(&mut self, session: Session, leaf: NodeId) {
+        self.packs_waiting_for_ack.remove(&session);
+        //fragments still queued for a route are not sent either
+        if let Some((_, queue)) = self.queued_packs.get_mut(&leaf) {
+            queue.retain(|pack| pack.session_id != session);
+            if queue.is_empty() {
+                self.queued_packs.remove(&leaf);
+            }
+        }
+        let message = self.messages_waiting_for_ack.remove(&session);
+        let chat_message = self.session_messages.remove(&session);
+        if let (Some(Message::ReqChatSend { to, .. }), Some((msg_id, SessionKind::Content))) =
+            (message, chat_message)
+        {
+            self.notify_frontend(UpdateMessageStatus(leaf, to, msg_id, FailedToSend));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use client_lib::communication::TUICommand::UpdateMessageContent;
+    use client_lib::communication::{MessageContent, MessageID, MessageStatus, TUICommand};
+    use crossbeam_channel::{unbounded, Receiver};
+    use std::collections::HashMap;
+    use wg_2024::packet::{Nack, NackType, Packet, PacketType};
+
+    const CLIENT: NodeId = 1;
+    const DRONE: NodeId = 11;
+    const SERVER: NodeId = 20;
+    const PEER: NodeId = 2;
+    const MSG_ID: MessageID = 7;
+
+    //a client whose only neighbour is DRONE, its clock started at `start`
+    fn network(start: Instant) -> (Network, Receiver<Packet>) {
+        let (packet_send, packet_recv) = unbounded();
+        let (controller_send, _) = unbounded();
+        let neighbours = HashMap::from([(DRONE, packet_send)]);
+        let mut network = Network::new(CLIENT, neighbours, controller_send);
+        network.handle_tick(start);
+        (network, packet_recv)
+    }
+
+    fn send_chat_message(network: &mut Network) {
+        let content = MessageContent::TextMessage("hello".to_string());
+        network.remember_frontend(UpdateMessageContent(SERVER, PEER, MSG_ID, content, 0));
+        let message = Message::ReqChatSend {
+            to: PEER,
+            chat_msg: b"hello".to_vec(),
+        };
+        network.send_message(message, SERVER, Some((MSG_ID, SessionKind::Content)));
+    }
+
+    fn fragments_sent(packet_recv: &Receiver<Packet>) -> usize {
+        packet_recv
+            .try_iter()
+            .filter(|p| matches!(p.pack_type, PacketType::MsgFragment(_)))
+            .count()
+    }
+
+    fn status(network: &Network) -> Option<MessageStatus> {
+        network
+            .frontend_mirror
+            .replay()
+            .into_iter()
+            .find_map(|command| match command {
+                TUICommand::UpdateMessageStatus(_, _, MSG_ID, status) => Some(status),
+                _ => None,
+            })
+    }
+
+    //ticks the clock up to `until` ms after `start`, returning when fragments left
+    fn run_until(
+        network: &mut Network,
+        packet_recv: &Receiver<Packet>,
+        start: Instant,
+        until: u64,
+    ) -> Vec<u64> {
+        let mut sends = Vec::new();
+        let tick = RETRANSMISSION_TICK.as_millis() as u64;
+        let from = (network.last_tick - start).as_millis() as u64 + tick;
+        for ms in (from..=until).step_by(tick as usize) {
+            network.handle_tick(start + Duration::from_millis(ms));
+            if fragments_sent(packet_recv) > 0 {
+                sends.push(ms);
+            }
+        }
+        sends
+    }
+
+    #[test]
+    fn waits_double_from_the_send_time() {
+        let start = Instant::now();
+        let (mut network, packet_recv) = network(start);
+        network
+            .paths_to_leafs
+            .insert(SERVER, Some(vec![CLIENT, DRONE, SERVER]));
+
+        send_chat_message(&mut network);
+        assert_eq!(fragments_sent(&packet_recv), 1);
+
+        let sends = run_until(&mut network, &packet_recv, start, 20_000);
+        assert_eq!(sends, vec![500, 1_500, 3_500, 7_500, 15_500]);
+    }
+
+    #[test]
+    fn fails_after_the_last_attempt() {
+        let start = Instant::now();
+        let (mut network, packet_recv) = network(start);
+        network
+            .paths_to_leafs
+            .insert(SERVER, Some(vec![CLIENT, DRONE, SERVER]));
+        send_chat_message(&mut network);
+
+        run_until(&mut network, &packet_recv, start, 31_400);
+        assert_eq!(status(&network), None);
+
+        let sends = run_until(&mut network, &packet_recv, start, 31_500);
+        assert!(sends.is_empty());
+        assert_eq!(status(&network), Some(MessageStatus::FailedToSend));
+        assert!(network.messages_waiting_for_ack.is_empty());
+        assert!(network.retransmission_timers.is_empty());
+        assert!(network.packs_waiting_for_ack.is_empty());
+    }
+
+    #[test]
+    fn a_session_never_sent_fails_too() {
+        let start = Instant::now();
+        let (mut network, packet_recv) = network(start);
+        send_chat_message(&mut network);
+        assert_eq!(fragments_sent(&packet_recv), 0);
+
+        let sends = run_until(&mut network, &packet_recv, start, 31_500);
+        assert!(sends.is_empty());
+        assert_eq!(status(&network), Some(MessageStatus::FailedToSend));
+        assert!(network.queued_packs.is_empty());
+        assert!(network.retransmission_timers.is_empty());
+    }
+
+    #[test]
+    fn a_nacked_session_waiting_for_a_route_fails() {
+        let start = Instant::now();
+        let (mut network, packet_recv) = network(start);
+        network
+            .paths_to_leafs
+            .insert(SERVER, Some(vec![CLIENT, DRONE, SERVER]));
+        send_chat_message(&mut network);
+        assert_eq!(fragments_sent(&packet_recv), 1);
+
+        //the route is lost and the only fragment comes back: it is queued, not waiting for an ack
+        network.paths_to_leafs.insert(SERVER, None);
+        let nack = Nack {
+            fragment_index: 0,
+            nack_type: NackType::Dropped,
+        };
+        let routing = SourceRoutingHeader::new(vec![DRONE, CLIENT], 1);
+        network.handle_packet(Packet::new_nack(routing, 0, nack));
+        assert!(network.packs_waiting_for_ack.is_empty());
+        assert!(network.queued_packs.contains_key(&SERVER));
+
+        run_until(&mut network, &packet_recv, start, 31_400);
+        assert_eq!(status(&network), None);
+        run_until(&mut network, &packet_recv, start, 31_500);
+        assert_eq!(status(&network), Some(MessageStatus::FailedToSend));
+        assert!(network.queued_packs.is_empty());
+    }
+}
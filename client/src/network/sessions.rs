@@ -0,0 +1,22 @@
+use crate::network::Network;
+use common_structs::types::Session;
+
+//what a chat session carries, so its acks can be mapped back to the chat message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SessionKind {
+    Content,
+    Edit,
+    Read,
+    Receipt,
+    Delete,
+    Reaction,
+}
+
+impl Network {
+    //hands out a session id never used before by this client
+    pub(super) fn next_session(&mut self) -> Session {
+        let session = self.current_session;
+        self.current_session += 1;
+        session
+    }
+}
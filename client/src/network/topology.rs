@@ -1,8 +1,66 @@
 use crate::network::Network;
 use petgraph::algo::astar;
+use petgraph::Direction::Incoming;
 use wg_2024::network::NodeId;
 
+//cost of going through a node that never drops
+const WEIGHT_SCALE: f64 = 100.0;
+//how much a single ack/nack moves the drop estimate of a drone
+const DROP_ESTIMATE_ALPHA: f64 = 0.2;
+//caps the estimate so a bad drone is expensive but never unreachable
+const MAX_DROP_ESTIMATE: f64 = 0.95;
+
 impl Network {
+    //weight of every edge entering the node: a hop plus -ln(delivery probability),
+    //so the cheapest path is the one with the best expected delivery
+    pub(super) fn node_weight(&self, node: NodeId) -> i32 {
+        let drop_rate = self.drop_estimates.get(&node).copied().unwrap_or(0.0);
+        let delivery_cost = -(1.0 - drop_rate).ln();
+        (WEIGHT_SCALE * (1.0 + delivery_cost)).round() as i32
+    }
+
+    //a drone dropped one of our fragments
+    pub(super) fn record_drop(&mut self, drone: NodeId) {
+        if self.update_drop_estimate(drone, 1.0) {
+            self.update_reachable_paths();
+        }
+    }
+
+    //every drone in the path forwarded a packet correctly
+    pub(super) fn record_delivery(&mut self, drones: &[NodeId]) {
+        let mut reweighted = false;
+        for drone in drones {
+            if self.drop_estimates.contains_key(drone) {
+                reweighted |= self.update_drop_estimate(*drone, 0.0);
+            }
+        }
+        if reweighted {
+            self.update_reachable_paths();
+        }
+    }
+
+    //returns whether the weight of the drone changed, routes only need to be found again then
+    fn update_drop_estimate(&mut self, drone: NodeId, sample: f64) -> bool {
+        let old_weight = self.node_weight(drone);
+        let estimate = self.drop_estimates.entry(drone).or_insert(0.0);
+        *estimate = (*estimate * (1.0 - DROP_ESTIMATE_ALPHA) + sample * DROP_ESTIMATE_ALPHA)
+            .min(MAX_DROP_ESTIMATE);
+
+        let weight = self.node_weight(drone);
+        if weight == old_weight {
+            return false;
+        }
+        //reweight the edges entering the drone
+        let neighbors = self
+            .topology
+            .neighbors_directed(drone, Incoming)
+            .collect::<Vec<_>>();
+        for neighbor in neighbors {
+            self.topology.add_edge(neighbor, drone, weight);
+        }
+        true
+    }
+
     //finds out if nodes that were reachable before are now not reachable anymore
     pub(super) fn update_reachable_paths(&mut self) {
         //gets all known leafs' ids
@@ -19,7 +77,7 @@ impl Network {
                     &self.topology,
                     self.id,
                     |finish| finish == leaf,
-                    |_| 1,
+                    |(_, _, weight)| *weight,
                     |_| 0,
                 );
                 if let Some((_, path)) = path {
@@ -30,7 +88,7 @@ impl Network {
                     //remove path from leaf and flood
                     self.paths_to_leafs.insert(leaf, None);
                     //println!("flood: removed route to {}", leaf);
-                    self.initiate_flood();
+                    self.request_flood();
                 }
             }
         }
@@ -53,7 +111,7 @@ impl Network {
                     &self.topology,
                     self.id,
                     |finish| finish == leaf,
-                    |_| 1,
+                    |(_, _, weight)| *weight,
                     |_| 0,
                 );
                 //if found add path to known paths and try sending any queued messages for that leaf
@@ -78,9 +136,9 @@ impl Network {
             let a = window[0];
             let b = window[1];
 
-            self.topology.add_edge(a, b, 1);
+            self.topology.add_edge(a, b, self.node_weight(b));
             if i != 0 && !(is_last_leaf && i == last_index) {
-                self.topology.add_edge(b, a, 1);
+                self.topology.add_edge(b, a, self.node_weight(a));
             }
             //println!("topology: {:?}", self.topology);
         }
@@ -88,3 +146,64 @@ impl Network {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::unbounded;
+    use std::collections::HashMap;
+
+    const CLIENT: NodeId = 1;
+    const SERVER: NodeId = 20;
+    const SHORT: [NodeId; 3] = [CLIENT, 11, SERVER];
+    const LONG: [NodeId; 4] = [CLIENT, 12, 13, SERVER];
+
+    //a client that knows a two hop and a three hop path to SERVER
+    fn network() -> Network {
+        let (controller_send, _) = unbounded();
+        let mut network = Network::new(CLIENT, HashMap::new(), controller_send);
+        network.add_path(&SHORT, true).unwrap();
+        network.add_path(&LONG, true).unwrap();
+        network.paths_to_leafs.insert(SERVER, None);
+        network.update_unreachable_paths();
+        network
+    }
+
+    fn route(network: &Network) -> Vec<NodeId> {
+        network.paths_to_leafs[&SERVER].clone().unwrap()
+    }
+
+    #[test]
+    fn a_lossy_drone_is_avoided_for_a_longer_reliable_path() {
+        let mut network = network();
+        assert_eq!(route(&network), SHORT);
+
+        //a single drop is not worth an extra hop
+        network.record_drop(11);
+        assert_eq!(route(&network), SHORT);
+
+        for _ in 0..4 {
+            network.record_drop(11);
+        }
+        assert_eq!(route(&network), LONG);
+
+        //once the drone behaves again it gets the traffic back
+        for _ in 0..20 {
+            network.record_delivery(&[11]);
+        }
+        assert_eq!(route(&network), SHORT);
+    }
+
+    #[test]
+    fn routes_are_only_searched_again_when_a_weight_changes() {
+        let mut network = network();
+        //a route astar would not pick, left alone as long as no weight moves
+        network.paths_to_leafs.insert(SERVER, Some(LONG.to_vec()));
+
+        network.record_delivery(&[11, 12, 13]);
+        assert_eq!(route(&network), LONG);
+
+        network.record_drop(12);
+        assert_eq!(route(&network), SHORT);
+    }
+}
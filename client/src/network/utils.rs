@@ -5,9 +5,6 @@ use wg_2024::network::{NodeId, SourceRoutingHeader};
 use wg_2024::packet::Packet;
 
 impl Network {
-    pub fn get_id(&self) -> NodeId {
-        self.id
-    }
     pub fn check_queued(&mut self, leaf: NodeId) {
         let packs = self.queued_packs.remove(&leaf);
         if let Some(packs) = packs {
@@ -31,8 +28,9 @@ impl Network {
 
     pub fn add_sender(&mut self, id: NodeId, sender: Sender<Packet>) {
         self.packet_send.insert(id, sender);
-        self.topology.add_edge(self.id, id, 1);
-        self.topology.add_edge(id, self.id, 1);
+        let (weight_out, weight_in) = (self.node_weight(id), self.node_weight(self.id));
+        self.topology.add_edge(self.id, id, weight_out);
+        self.topology.add_edge(id, self.id, weight_in);
         self.update_unreachable_paths();
     }
     pub fn remove_sender(&mut self, id: &NodeId) {
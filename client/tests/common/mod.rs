@@ -0,0 +1,837 @@
+//in-process network of fake drones and chat servers to drive clients headless
+#![allow(dead_code)]
+
+use client::{Client, FrontendMode};
+use client_lib::auth::Auth;
+use client_lib::codec::WireCodec;
+use client_lib::communication::{handshake, Capability, Connection, TUICommand, TUIEvent};
+use client_lib::ClientError;
+use common_structs::leaf::{Leaf, LeafCommand, LeafEvent};
+use common_structs::message::{Message, ServerType};
+use crossbeam_channel::{select, unbounded, Receiver, RecvTimeoutError, Sender};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use wg_2024::network::{NodeId, SourceRoutingHeader};
+use wg_2024::packet::{
+    FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType,
+};
+
+//real time a test waits for an expected command
+const EXPECT_TIMEOUT: Duration = Duration::from_secs(10);
+//real time given to the clients to react before the clock moves on
+const STEP_WAIT: Duration = Duration::from_millis(5);
+//granularity of the fake clock, matches the client tick
+const CLOCK_STEP: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Copy, Clone)]
+pub struct DroneBehaviour {
+    //probability of dropping a fragment
+    pub pdr: f64,
+    //whether a dropped fragment is reported with a Dropped nack
+    pub nack_on_drop: bool,
+}
+
+impl DroneBehaviour {
+    pub fn reliable() -> Self {
+        DroneBehaviour {
+            pdr: 0.0,
+            nack_on_drop: true,
+        }
+    }
+
+    pub fn lossy(pdr: f64) -> Self {
+        DroneBehaviour {
+            pdr,
+            nack_on_drop: true,
+        }
+    }
+
+    //drops everything without telling anyone
+    pub fn black_hole() -> Self {
+        DroneBehaviour {
+            pdr: 1.0,
+            nack_on_drop: false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Client,
+    //client started without a frontend, one can be attached later
+    DetachedClient,
+    Drone,
+    Server,
+}
+
+enum NodeCommand {
+    AddSender(NodeId, Sender<Packet>),
+    RemoveSender(NodeId),
+    SetBehaviour(DroneBehaviour),
+}
+
+//what the fake frontends announce in the handshake unless told otherwise
+const FRONTEND_CAPABILITIES: &[Capability] = &[
+    Capability::Drawings,
+    Capability::Reactions,
+    Capability::BinaryCodec,
+];
+
+pub struct SimulationBuilder {
+    nodes: Vec<(NodeId, Kind, DroneBehaviour)>,
+    links: Vec<(NodeId, NodeId)>,
+    frontend_capabilities: Vec<Capability>,
+    frontend_token: Option<String>,
+    data_dir: Option<PathBuf>,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        SimulationBuilder {
+            nodes: Vec::new(),
+            links: Vec::new(),
+            frontend_capabilities: FRONTEND_CAPABILITIES.to_vec(),
+            frontend_token: None,
+            data_dir: None,
+        }
+    }
+
+    //clients keep what must survive a restart there
+    pub fn data_dir(mut self, dir: &Path) -> Self {
+        self.data_dir = Some(dir.to_path_buf());
+        self
+    }
+
+    //clients only accept frontends presenting the token, the fake ones do
+    pub fn frontend_token(mut self, token: &str) -> Self {
+        self.frontend_token = Some(token.to_string());
+        self
+    }
+
+    //frontends behave like builds knowing only these capabilities
+    pub fn frontend_capabilities(mut self, capabilities: &[Capability]) -> Self {
+        self.frontend_capabilities = capabilities.to_vec();
+        self
+    }
+
+    pub fn client(mut self, id: NodeId) -> Self {
+        self.nodes
+            .push((id, Kind::Client, DroneBehaviour::reliable()));
+        self
+    }
+
+    pub fn detached_client(mut self, id: NodeId) -> Self {
+        self.nodes
+            .push((id, Kind::DetachedClient, DroneBehaviour::reliable()));
+        self
+    }
+
+    pub fn server(mut self, id: NodeId) -> Self {
+        self.nodes
+            .push((id, Kind::Server, DroneBehaviour::reliable()));
+        self
+    }
+
+    pub fn drone(mut self, id: NodeId, behaviour: DroneBehaviour) -> Self {
+        self.nodes.push((id, Kind::Drone, behaviour));
+        self
+    }
+
+    pub fn link(mut self, a: NodeId, b: NodeId) -> Self {
+        self.links.push((a, b));
+        self
+    }
+
+    pub fn build(self) -> Simulation {
+        let mut packet_channels = HashMap::new();
+        for (id, _, _) in &self.nodes {
+            packet_channels.insert(*id, unbounded::<Packet>());
+        }
+        let mut neighbours: HashMap<NodeId, HashMap<NodeId, Sender<Packet>>> = HashMap::new();
+        for (a, b) in &self.links {
+            neighbours
+                .entry(*a)
+                .or_default()
+                .insert(*b, packet_channels[b].0.clone());
+            neighbours
+                .entry(*b)
+                .or_default()
+                .insert(*a, packet_channels[a].0.clone());
+        }
+
+        let start = Instant::now();
+        let mut simulation = Simulation {
+            now: start,
+            clients: HashMap::new(),
+            commands: HashMap::new(),
+            packet_senders: HashMap::new(),
+            frontend_capabilities: self.frontend_capabilities.clone(),
+            frontend_token: self.frontend_token.clone(),
+        };
+
+        //drones and servers first, so the clients' first flood finds them running
+        let mut clients = Vec::new();
+        for (id, kind, behaviour) in self.nodes.iter().copied() {
+            let packet_recv = packet_channels[&id].1.clone();
+            let packet_send = neighbours.remove(&id).unwrap_or_default();
+            simulation
+                .packet_senders
+                .insert(id, packet_channels[&id].0.clone());
+            match kind {
+                Kind::Drone => {
+                    let (command_send, command_recv) = unbounded();
+                    simulation.commands.insert(id, command_send);
+                    let mut drone = FakeDrone::new(id, behaviour, packet_recv, command_recv);
+                    drone.neighbours = packet_send;
+                    thread::spawn(move || drone.run());
+                }
+                Kind::Server => {
+                    let (command_send, command_recv) = unbounded();
+                    simulation.commands.insert(id, command_send);
+                    let mut server = FakeChatServer::new(id, packet_recv, command_recv);
+                    server.neighbours = packet_send;
+                    thread::spawn(move || server.run());
+                }
+                Kind::Client | Kind::DetachedClient => {
+                    clients.push((id, kind, packet_recv, packet_send))
+                }
+            }
+        }
+        for (id, kind, packet_recv, packet_send) in clients {
+            let detached = kind == Kind::DetachedClient;
+            let handle = ClientHandle::spawn(id, start, detached, &self, packet_recv, packet_send);
+            simulation.clients.insert(id, handle);
+        }
+        simulation
+    }
+}
+
+pub struct Simulation {
+    now: Instant,
+    clients: HashMap<NodeId, ClientHandle>,
+    commands: HashMap<NodeId, Sender<NodeCommand>>,
+    packet_senders: HashMap<NodeId, Sender<Packet>>,
+    frontend_capabilities: Vec<Capability>,
+    frontend_token: Option<String>,
+}
+
+impl Simulation {
+    pub fn frontend(&mut self, client: NodeId) -> &mut Frontend {
+        self.clients
+            .get_mut(&client)
+            .expect("unknown client")
+            .frontend
+            .as_mut()
+            .expect("no frontend attached")
+    }
+
+    //connects a frontend to a client started without one
+    pub fn attach_frontend(&mut self, client: NodeId) {
+        let token = self.frontend_token.clone();
+        let auth = match &token {
+            Some(token) => Auth::Present(token),
+            None => Auth::Open,
+        };
+        self.try_attach_frontend(client, auth).unwrap();
+    }
+
+    //connects a frontend presenting whatever the test wants
+    pub fn try_attach_frontend(&mut self, client: NodeId, auth: Auth) -> Result<(), ClientError> {
+        let stream = TcpStream::connect(self.listen_address(client)).unwrap();
+        let frontend = Frontend::new(stream, &self.frontend_capabilities, auth)?;
+        let handle = self.clients.get_mut(&client).expect("unknown client");
+        handle.frontend = Some(frontend);
+        Ok(())
+    }
+
+    //where a detached client waits for frontends
+    pub fn listen_address(&self, client: NodeId) -> SocketAddr {
+        let handle = self.clients.get(&client).expect("unknown client");
+        handle.listen_address.expect("client has a frontend")
+    }
+
+    //sends the client the controller's Kill
+    pub fn kill(&mut self, client: NodeId) {
+        let handle = self.clients.get(&client).expect("unknown client");
+        let _ = handle.controller_send.send(LeafCommand::Kill);
+    }
+
+    //whether the client returns from run in time, with every thread it started joined
+    pub fn stops_within(&self, client: NodeId, timeout: Duration) -> bool {
+        let handle = self.clients.get(&client).expect("unknown client");
+        let deadline = Instant::now() + timeout;
+        while !handle.thread.is_finished() {
+            if Instant::now() > deadline {
+                return false;
+            }
+            thread::sleep(STEP_WAIT);
+        }
+        true
+    }
+
+    //closes the connection of the client's frontend, as if the TUI crashed
+    pub fn disconnect_frontend(&mut self, client: NodeId) {
+        let handle = self.clients.get_mut(&client).expect("unknown client");
+        if let Some(frontend) = handle.frontend.take() {
+            frontend.stream.shutdown();
+        }
+    }
+
+    //moves the fake clock of every client forward, one tick at a time
+    pub fn advance_clock(&mut self, by: Duration) {
+        let target = self.now + by;
+        while self.now < target {
+            self.step_clock();
+        }
+    }
+
+    fn step_clock(&mut self) {
+        self.now += CLOCK_STEP;
+        for client in self.clients.values() {
+            let _ = client.tick_send.send(self.now);
+        }
+    }
+
+    //skips commands until one matches, letting the clock run meanwhile
+    pub fn expect(
+        &mut self,
+        client: NodeId,
+        what: &str,
+        matches: impl Fn(&TUICommand) -> bool,
+    ) -> TUICommand {
+        let deadline = Instant::now() + EXPECT_TIMEOUT;
+        while Instant::now() < deadline {
+            while let Some(command) = self.frontend(client).next(STEP_WAIT) {
+                if matches(&command) {
+                    return command;
+                }
+            }
+            self.step_clock();
+        }
+        panic!("client {} never received {}", client, what);
+    }
+
+    //checks that nothing matching arrives within the given fake time
+    pub fn expect_none(
+        &mut self,
+        client: NodeId,
+        what: &str,
+        within: Duration,
+        matches: impl Fn(&TUICommand) -> bool,
+    ) {
+        let target = self.now + within;
+        while self.now < target {
+            while let Some(command) = self.frontend(client).next(STEP_WAIT) {
+                if matches(&command) {
+                    panic!("client {} received {}", client, what);
+                }
+            }
+            self.step_clock();
+        }
+    }
+
+    pub fn set_behaviour(&mut self, drone: NodeId, behaviour: DroneBehaviour) {
+        let _ = self.commands[&drone].send(NodeCommand::SetBehaviour(behaviour));
+    }
+
+    //cuts the link between two nodes, telling both sides like the controller would
+    pub fn remove_link(&mut self, a: NodeId, b: NodeId) {
+        self.remove_sender(a, b);
+        self.remove_sender(b, a);
+    }
+
+    pub fn add_link(&mut self, a: NodeId, b: NodeId) {
+        self.add_sender(a, b);
+        self.add_sender(b, a);
+    }
+
+    fn remove_sender(&mut self, node: NodeId, neighbour: NodeId) {
+        if let Some(client) = self.clients.get(&node) {
+            let _ = client
+                .controller_send
+                .send(LeafCommand::RemoveSender(neighbour));
+        } else if let Some(commands) = self.commands.get(&node) {
+            let _ = commands.send(NodeCommand::RemoveSender(neighbour));
+        }
+    }
+
+    fn add_sender(&mut self, node: NodeId, neighbour: NodeId) {
+        let sender = self.packet_senders[&neighbour].clone();
+        if let Some(client) = self.clients.get(&node) {
+            let _ = client
+                .controller_send
+                .send(LeafCommand::AddSender(neighbour, sender));
+        } else if let Some(commands) = self.commands.get(&node) {
+            let _ = commands.send(NodeCommand::AddSender(neighbour, sender));
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        for client in self.clients.values() {
+            let _ = client.controller_send.send(LeafCommand::Kill);
+        }
+    }
+}
+
+struct ClientHandle {
+    controller_send: Sender<LeafCommand>,
+    tick_send: Sender<Instant>,
+    frontend: Option<Frontend>,
+    //where a detached client waits for its frontend
+    listen_address: Option<SocketAddr>,
+    //keeps the event channel open, the client stops sending if it is closed
+    _controller_recv: Receiver<LeafEvent>,
+    //returns once the client is killed
+    thread: JoinHandle<()>,
+}
+
+impl ClientHandle {
+    fn spawn(
+        id: NodeId,
+        start: Instant,
+        detached: bool,
+        setup: &SimulationBuilder,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+    ) -> Self {
+        let (event_send, event_recv) = unbounded();
+        let (controller_send, controller_recv) = unbounded();
+        let (tick_send, tick_recv) = unbounded();
+        let _ = tick_send.send(start);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (mode, frontend_stream, listen_address) = if detached {
+            (
+                FrontendMode::Listen(Box::new(listener)),
+                None,
+                Some(address),
+            )
+        } else {
+            let frontend_stream = TcpStream::connect(address).unwrap();
+            let (backend_stream, _) = listener.accept().unwrap();
+            (
+                FrontendMode::Stream(Box::new(backend_stream)),
+                Some(frontend_stream),
+                None,
+            )
+        };
+
+        let mut client = Client::new(id, event_send, controller_recv, packet_recv, packet_send)
+            .with_tick_receiver(tick_recv)
+            .with_frontend(mode);
+        let token = setup.frontend_token.as_deref();
+        if let Some(token) = token {
+            client = client.with_frontend_token(token.to_string());
+        }
+        if let Some(dir) = &setup.data_dir {
+            client = client.with_data_dir(dir.clone());
+        }
+        let thread = thread::spawn(move || client.run());
+        //the handshake needs the client running
+        let auth = match token {
+            Some(token) => Auth::Present(token),
+            None => Auth::Open,
+        };
+        let frontend = frontend_stream
+            .map(|stream| Frontend::new(stream, &setup.frontend_capabilities, auth).unwrap());
+
+        ClientHandle {
+            controller_send,
+            tick_send,
+            frontend,
+            listen_address,
+            _controller_recv: event_recv,
+            thread,
+        }
+    }
+}
+
+//stands in for the TUI at the other end of the client's stream
+pub struct Frontend {
+    stream: Connection,
+    commands: Receiver<TUICommand>,
+}
+
+impl Frontend {
+    fn new(
+        stream: TcpStream,
+        capabilities: &[Capability],
+        auth: Auth,
+    ) -> Result<Self, ClientError> {
+        let stream = handshake(Box::new(stream), capabilities, auth)?;
+        let (command_send, command_recv) = unbounded();
+        let mut reader = stream.try_clone().unwrap();
+        thread::spawn(move || {
+            while let Ok(command) = reader.receive::<TUICommand>() {
+                if command_send.send(command).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Frontend {
+            stream,
+            commands: command_recv,
+        })
+    }
+
+    pub fn send(&mut self, event: TUIEvent) {
+        self.stream.send(event).unwrap();
+    }
+
+    pub fn codec(&self) -> WireCodec {
+        self.stream.codec()
+    }
+
+    //next command sent by the client, if one arrives in time
+    pub fn next(&mut self, timeout: Duration) -> Option<TUICommand> {
+        self.commands.recv_timeout(timeout).ok()
+    }
+
+    //whether the client closes the stream in time, whatever it still sends before
+    pub fn closes_within(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.commands.recv_deadline(deadline) {
+                Ok(_) => {}
+                Err(RecvTimeoutError::Disconnected) => return true,
+                Err(RecvTimeoutError::Timeout) => return false,
+            }
+        }
+    }
+}
+
+//small deterministic generator, so lossy runs are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn reply_route(routing: &SourceRoutingHeader) -> SourceRoutingHeader {
+    let hops = routing.hops[..=routing.hop_index]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    SourceRoutingHeader::new(hops, 1)
+}
+
+fn flood_response(flood: &FloodRequest, session: u64) -> Packet {
+    let hops = flood.path_trace.iter().rev().map(|(id, _)| *id).collect();
+    Packet::new_flood_response(
+        SourceRoutingHeader::new(hops, 1),
+        session,
+        FloodResponse {
+            flood_id: flood.flood_id,
+            path_trace: flood.path_trace.clone(),
+        },
+    )
+}
+
+struct FakeDrone {
+    id: NodeId,
+    behaviour: DroneBehaviour,
+    rng: XorShift,
+    packet_recv: Receiver<Packet>,
+    commands: Receiver<NodeCommand>,
+    neighbours: HashMap<NodeId, Sender<Packet>>,
+    seen_floods: HashSet<(NodeId, u64)>,
+}
+
+impl FakeDrone {
+    fn new(
+        id: NodeId,
+        behaviour: DroneBehaviour,
+        packet_recv: Receiver<Packet>,
+        commands: Receiver<NodeCommand>,
+    ) -> Self {
+        FakeDrone {
+            id,
+            behaviour,
+            rng: XorShift(0x9E37_79B9_7F4A_7C15 ^ id as u64),
+            packet_recv,
+            commands,
+            neighbours: HashMap::new(),
+            seen_floods: HashSet::new(),
+        }
+    }
+
+    fn run(&mut self) {
+        loop {
+            select! {
+                recv(self.commands) -> command => match command {
+                    Ok(NodeCommand::AddSender(id, sender)) => {
+                        self.neighbours.insert(id, sender);
+                    }
+                    Ok(NodeCommand::RemoveSender(id)) => {
+                        self.neighbours.remove(&id);
+                    }
+                    Ok(NodeCommand::SetBehaviour(behaviour)) => self.behaviour = behaviour,
+                    Err(_) => return,
+                },
+                recv(self.packet_recv) -> packet => match packet {
+                    Ok(packet) => self.handle_packet(packet),
+                    Err(_) => return,
+                },
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, mut packet: Packet) {
+        if let PacketType::FloodRequest(flood) = packet.pack_type.clone() {
+            return self.handle_flood(packet, flood);
+        }
+        if packet.routing_header.current_hop() != Some(self.id) {
+            return;
+        }
+        let is_fragment = matches!(packet.pack_type, PacketType::MsgFragment(_));
+        if is_fragment && self.rng.next_f64() < self.behaviour.pdr {
+            if self.behaviour.nack_on_drop {
+                self.nack(&packet, NackType::Dropped);
+            }
+            return;
+        }
+
+        packet.routing_header.increase_hop_index();
+        let Some(next) = packet.routing_header.current_hop() else {
+            return;
+        };
+        match self.neighbours.get(&next) {
+            Some(sender) => {
+                let _ = sender.send(packet);
+            }
+            None => {
+                if is_fragment {
+                    packet.routing_header.hop_index -= 1;
+                    self.nack(&packet, NackType::ErrorInRouting(next));
+                }
+            }
+        }
+    }
+
+    fn nack(&self, packet: &Packet, nack_type: NackType) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            return;
+        };
+        let routing = reply_route(&packet.routing_header);
+        let nack = Packet::new_nack(
+            routing,
+            packet.session_id,
+            Nack {
+                fragment_index: fragment.fragment_index,
+                nack_type,
+            },
+        );
+        if let Some(sender) = self.neighbours.get(&nack.routing_header.hops[1]) {
+            let _ = sender.send(nack);
+        }
+    }
+
+    fn handle_flood(&mut self, packet: Packet, mut flood: FloodRequest) {
+        let previous = flood.path_trace.last().map(|(id, _)| *id);
+        flood.path_trace.push((self.id, NodeType::Drone));
+        let others = self
+            .neighbours
+            .iter()
+            .filter(|(id, _)| Some(**id) != previous)
+            .map(|(_, sender)| sender.clone())
+            .collect::<Vec<_>>();
+
+        if !self
+            .seen_floods
+            .insert((flood.initiator_id, flood.flood_id))
+            || others.is_empty()
+        {
+            let response = flood_response(&flood, packet.session_id);
+            if let Some(sender) = self.neighbours.get(&response.routing_header.hops[1]) {
+                let _ = sender.send(response);
+            }
+            return;
+        }
+        for sender in others {
+            let _ = sender.send(Packet::new_flood_request(
+                packet.routing_header.clone(),
+                packet.session_id,
+                flood.clone(),
+            ));
+        }
+    }
+}
+
+struct FakeChatServer {
+    id: NodeId,
+    packet_recv: Receiver<Packet>,
+    commands: Receiver<NodeCommand>,
+    neighbours: HashMap<NodeId, Sender<Packet>>,
+    partially_received: HashMap<(NodeId, u64), Vec<Option<Fragment>>>,
+    registered: BTreeSet<NodeId>,
+    //latest route each client reached the server with, reversed
+    routes: HashMap<NodeId, Vec<NodeId>>,
+    //fragments sent and not acked yet, resent on Dropped nacks
+    waiting_for_ack: HashMap<(u64, u64), Packet>,
+    next_session: u64,
+}
+
+impl FakeChatServer {
+    fn new(id: NodeId, packet_recv: Receiver<Packet>, commands: Receiver<NodeCommand>) -> Self {
+        FakeChatServer {
+            id,
+            packet_recv,
+            commands,
+            neighbours: HashMap::new(),
+            partially_received: HashMap::new(),
+            registered: BTreeSet::new(),
+            routes: HashMap::new(),
+            waiting_for_ack: HashMap::new(),
+            next_session: 0,
+        }
+    }
+
+    fn run(&mut self) {
+        loop {
+            select! {
+                recv(self.commands) -> command => match command {
+                    Ok(NodeCommand::AddSender(id, sender)) => {
+                        self.neighbours.insert(id, sender);
+                    }
+                    Ok(NodeCommand::RemoveSender(id)) => {
+                        self.neighbours.remove(&id);
+                    }
+                    Ok(NodeCommand::SetBehaviour(_)) => {}
+                    Err(_) => return,
+                },
+                recv(self.packet_recv) -> packet => match packet {
+                    Ok(packet) => self.handle_packet(packet),
+                    Err(_) => return,
+                },
+            }
+        }
+    }
+
+    fn forward(&self, packet: Packet) {
+        if let Some(sender) = packet
+            .routing_header
+            .hops
+            .get(1)
+            .and_then(|hop| self.neighbours.get(hop))
+        {
+            let _ = sender.send(packet);
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet) {
+        match packet.pack_type.clone() {
+            PacketType::FloodRequest(mut flood) => {
+                flood.path_trace.push((self.id, NodeType::Server));
+                self.forward(flood_response(&flood, packet.session_id));
+            }
+            PacketType::MsgFragment(fragment) => self.handle_fragment(packet, fragment),
+            PacketType::Ack(ack) => {
+                self.waiting_for_ack
+                    .remove(&(packet.session_id, ack.fragment_index));
+            }
+            PacketType::Nack(nack) => {
+                if let Some(pack) = self
+                    .waiting_for_ack
+                    .get(&(packet.session_id, nack.fragment_index))
+                {
+                    self.forward(pack.clone());
+                }
+            }
+            PacketType::FloodResponse(_) => {}
+        }
+    }
+
+    fn handle_fragment(&mut self, packet: Packet, fragment: Fragment) {
+        let source = packet.routing_header.hops[0];
+        let route = reply_route(&packet.routing_header);
+        self.routes.insert(source, route.hops.clone());
+        self.forward(Packet::new_ack(
+            route,
+            packet.session_id,
+            fragment.fragment_index,
+        ));
+
+        let key = (source, packet.session_id);
+        let total = fragment.total_n_fragments as usize;
+        let partial = self
+            .partially_received
+            .entry(key)
+            .or_insert_with(|| vec![None; total]);
+        let index = fragment.fragment_index as usize;
+        partial[index] = Some(fragment);
+        if partial.iter().any(Option::is_none) {
+            return;
+        }
+        let fragments = self
+            .partially_received
+            .remove(&key)
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+            .collect();
+        if let Ok(message) = Message::from_fragments(fragments) {
+            self.handle_message(source, message);
+        }
+    }
+
+    fn handle_message(&mut self, source: NodeId, message: Message) {
+        match message {
+            Message::ReqServerType => {
+                self.send(source, Message::RespServerType(ServerType::Chat));
+            }
+            Message::ReqChatRegistration => {
+                self.registered.insert(source);
+            }
+            Message::ReqChatClients => {
+                let clients = self.registered.iter().copied().collect();
+                self.send(source, Message::RespClientList(clients));
+            }
+            Message::ReqChatSend { to, chat_msg } => {
+                if self.registered.contains(&to) {
+                    self.send(
+                        to,
+                        Message::RespChatFrom {
+                            from: source,
+                            chat_msg,
+                        },
+                    );
+                } else {
+                    self.send(source, Message::ErrNotExistentClient);
+                }
+            }
+            _ => self.send(source, Message::ErrUnsupportedRequestType),
+        }
+    }
+
+    fn send(&mut self, to: NodeId, message: Message) {
+        let Some(route) = self.routes.get(&to).cloned() else {
+            return;
+        };
+        let session = self.next_session;
+        self.next_session += 1;
+        for fragment in message.into_fragments() {
+            let index = fragment.fragment_index;
+            let packet = Packet::new_fragment(
+                SourceRoutingHeader::new(route.clone(), 1),
+                session,
+                fragment,
+            );
+            self.waiting_for_ack
+                .insert((session, index), packet.clone());
+            self.forward(packet);
+        }
+    }
+}
@@ -0,0 +1,712 @@
+mod common;
+
+use client::{Client, FrontendMode};
+use client_lib::auth::{Auth, TOKEN_ENV};
+use client_lib::codec::WireCodec;
+use client_lib::communication::MessageContent::TextMessage;
+use client_lib::communication::MessageStatus::{
+    FailedToSend, ReadByPeer, ReceivedByPeer, ReceivedByServer,
+};
+use client_lib::communication::TUICommand::{
+    UpdateChatRoom, UpdateMessageContent, UpdateMessageReaction, UpdateMessageStatus, UpdateName,
+    UpdatePeerLastSeen, UpdatePeerName,
+};
+use client_lib::communication::TUIEvent::{
+    Dead, EditMessage, ReactToMessage, ReadMessage, RegisterToServer, RequestRoomList, SendMessage,
+    SetName,
+};
+use client_lib::communication::{
+    now, receive_message, send_message, Capability, Hello, MessageStatus, Reaction, TUICommand,
+    PROTOCOL_VERSION,
+};
+use client_lib::sys::{LaunchChoice, Terminal, TERMINAL_ENV};
+use client_lib::transport::{memory_listener, memory_pair};
+use client_lib::ClientError;
+use common::{DroneBehaviour, Simulation, SimulationBuilder};
+use common_structs::leaf::{Leaf, LeafCommand};
+use crossbeam_channel::unbounded;
+use std::collections::{HashMap, HashSet};
+use std::mem::discriminant;
+use std::net::TcpStream;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{env, fs, process, thread};
+use wg_2024::network::NodeId;
+
+//real time a client is given to close its streams and stop
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+//waits for the server to be discovered and registers the client to it
+fn register(simulation: &mut Simulation, client: NodeId, server: NodeId) {
+    simulation.expect(
+        client,
+        "server discovery",
+        |c| matches!(c, UpdateChatRoom(s, Some(false), Some(true)) if *s == server),
+    );
+    simulation.frontend(client).send(RegisterToServer(server));
+    simulation.expect(
+        client,
+        "registration",
+        |c| matches!(c, UpdateChatRoom(s, Some(true), Some(true)) if *s == server),
+    );
+}
+
+fn send_text(simulation: &mut Simulation, from: NodeId, server: NodeId, to: NodeId, id: u64) {
+    simulation.frontend(from).send(SendMessage(
+        server,
+        to,
+        id,
+        TextMessage(format!("message {}", id)),
+    ));
+}
+
+fn expect_text(simulation: &mut Simulation, client: NodeId, server: NodeId, from: NodeId, id: u64) {
+    let text = format!("message {}", id);
+    simulation.expect(client, &text, |c| {
+        matches!(c, UpdateMessageContent(s, f, m, TextMessage(t), _)
+            if *s == server && *f == from && *m == id && *t == text)
+    });
+}
+
+fn expect_edit(simulation: &mut Simulation, client: NodeId, peer: NodeId, id: u64) {
+    let text = format!("message {}, edited", id);
+    simulation.expect(client, &text, |c| {
+        matches!(c, UpdateMessageContent(_, p, m, TextMessage(t), _)
+            if *p == peer && *m == id && *t == text)
+    });
+}
+
+fn expect_status(simulation: &mut Simulation, client: NodeId, id: u64, status: MessageStatus) {
+    simulation.expect(client, &format!("{:?}", status), |c| {
+        matches!(c, UpdateMessageStatus(_, _, m, s)
+            if *m == id && discriminant(s) == discriminant(&status))
+    });
+}
+
+#[test]
+fn message_is_delivered_through_the_server() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .drone(12, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 12)
+        .link(11, 12)
+        .link(12, 20)
+        .build();
+    assert_eq!(simulation.frontend(1).codec(), WireCodec::Binary);
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    simulation.frontend(2).send(RequestRoomList(20));
+    simulation.expect(2, "peer list", |c| matches!(c, UpdatePeerName(20, 1, None)));
+
+    send_text(&mut simulation, 1, 20, 2, 7);
+    expect_status(&mut simulation, 1, 7, ReceivedByServer);
+    expect_text(&mut simulation, 2, 20, 1, 7);
+}
+
+#[test]
+fn reaction_and_its_removal_reach_the_peer() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+    send_text(&mut simulation, 1, 20, 2, 7);
+    expect_text(&mut simulation, 2, 20, 1, 7);
+
+    simulation
+        .frontend(2)
+        .send(ReactToMessage(20, 1, 7, Some(Reaction::Heart)));
+    simulation.expect(1, "the reaction", |c| {
+        matches!(c, UpdateMessageReaction(20, 2, 7, Some(Reaction::Heart)))
+    });
+    simulation.frontend(2).send(ReactToMessage(20, 1, 7, None));
+    simulation.expect(1, "the reaction removal", |c| {
+        matches!(c, UpdateMessageReaction(20, 2, 7, None))
+    });
+}
+
+#[test]
+fn message_carries_its_send_time_and_the_peer_is_seen() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    let before = now();
+    send_text(&mut simulation, 1, 20, 2, 7);
+    let message = simulation.expect(2, "the message", |c| {
+        matches!(c, UpdateMessageContent(20, 1, 7, _, _))
+    });
+    let UpdateMessageContent(_, _, _, _, sent) = message else {
+        unreachable!()
+    };
+    assert!(before <= sent && sent <= now(), "sent at {}", sent);
+    simulation.expect(
+        2,
+        "the peer last seen",
+        |c| matches!(c, UpdatePeerLastSeen(20, 1, seen) if *seen >= sent),
+    );
+}
+
+#[test]
+fn edit_reaches_the_peer() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+    send_text(&mut simulation, 1, 20, 2, 7);
+    expect_text(&mut simulation, 2, 20, 1, 7);
+
+    simulation
+        .frontend(1)
+        .send(EditMessage(20, 2, 7, TextMessage("edited".to_string())));
+    simulation.expect(
+        2,
+        "the edit",
+        |c| matches!(c, UpdateMessageContent(20, 1, 7, TextMessage(t), _) if t == "edited"),
+    );
+}
+
+#[test]
+fn receipts_only_move_the_status_forward() {
+    let mut simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    simulation.attach_frontend(1);
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    //the receiving backend confirms the message without its frontend doing anything
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_status(&mut simulation, 1, 1, ReceivedByServer);
+    expect_status(&mut simulation, 1, 1, ReceivedByPeer);
+    expect_text(&mut simulation, 2, 20, 1, 1);
+    simulation.frontend(2).send(ReadMessage(20, 1, 1));
+    expect_status(&mut simulation, 1, 1, ReadByPeer);
+
+    //an edit gets a new receipt from the peer, older than the read one
+    simulation
+        .frontend(1)
+        .send(EditMessage(20, 2, 1, TextMessage("edited".to_string())));
+    expect_status(&mut simulation, 1, 1, ReceivedByPeer);
+
+    //whatever arrived in between, a new frontend sees the furthest status
+    simulation.disconnect_frontend(1);
+    simulation.attach_frontend(1);
+    let status = simulation.expect(1, "the message status", |c| {
+        matches!(c, UpdateMessageStatus(20, 2, 1, _))
+    });
+    assert_eq!(status, UpdateMessageStatus(20, 2, 1, ReadByPeer));
+    simulation.expect(
+        1,
+        "the edited message",
+        |c| matches!(c, UpdateMessageContent(20, 2, 1, TextMessage(t), _) if t == "edited"),
+    );
+}
+
+#[test]
+fn names_reach_known_and_new_peers() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+    //2 finds 1 in the room while registering
+    simulation.expect(
+        1,
+        "the name of 2",
+        |c| matches!(c, UpdatePeerName(20, 2, Some(n)) if n == "client_2"),
+    );
+
+    simulation.frontend(2).send(SetName("bob".to_string()));
+    simulation.expect(
+        1,
+        "the new name of 2",
+        |c| matches!(c, UpdatePeerName(20, 2, Some(n)) if n == "bob"),
+    );
+    //a name set back is not mistaken for a resent one
+    simulation.frontend(2).send(SetName("client_2".to_string()));
+    simulation.expect(
+        1,
+        "the old name of 2",
+        |c| matches!(c, UpdatePeerName(20, 2, Some(n)) if n == "client_2"),
+    );
+
+    //1 only meets 2 when it asks for the room list again
+    simulation.frontend(1).send(RequestRoomList(20));
+    simulation.expect(
+        2,
+        "the name of 1",
+        |c| matches!(c, UpdatePeerName(20, 1, Some(n)) if n == "client_1"),
+    );
+}
+
+#[test]
+fn chosen_name_survives_a_restart() {
+    let dir = env::temp_dir().join(format!("pictorust-names-{}", process::id()));
+    {
+        let mut simulation = SimulationBuilder::new().data_dir(&dir).client(1).build();
+        simulation.expect(
+            1,
+            "the default name",
+            |c| matches!(c, UpdateName(n) if n == "client_1"),
+        );
+        simulation.frontend(1).send(SetName("alice".to_string()));
+        simulation.expect(
+            1,
+            "the new name",
+            |c| matches!(c, UpdateName(n) if n == "alice"),
+        );
+    }
+    let mut simulation = SimulationBuilder::new().data_dir(&dir).client(1).build();
+    simulation.expect(
+        1,
+        "the saved name",
+        |c| matches!(c, UpdateName(n) if n == "alice"),
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn frontend_without_binary_codec_falls_back_to_json() {
+    let mut simulation = SimulationBuilder::new()
+        .frontend_capabilities(&[Capability::Drawings, Capability::Reactions])
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    assert_eq!(simulation.frontend(1).codec(), WireCodec::Json);
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_text(&mut simulation, 2, 20, 1, 1);
+}
+
+#[test]
+fn detached_client_replays_state_to_a_late_frontend() {
+    let mut simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(11, 20)
+        .build();
+    //the server is discovered while nobody is watching
+    simulation.advance_clock(Duration::from_secs(1));
+
+    simulation.attach_frontend(1);
+    simulation.expect(
+        1,
+        "the client name",
+        |c| matches!(c, UpdateName(n) if n == "client_1"),
+    );
+    register(&mut simulation, 1, 20);
+}
+
+#[test]
+fn reconnected_frontend_gets_the_full_state() {
+    let mut simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    simulation.attach_frontend(1);
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+    simulation.frontend(1).send(RequestRoomList(20));
+    simulation.expect(1, "peer list", |c| matches!(c, UpdatePeerName(20, 2, None)));
+
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_status(&mut simulation, 1, 1, ReceivedByPeer);
+    send_text(&mut simulation, 2, 20, 1, 2);
+    expect_text(&mut simulation, 1, 20, 2, 2);
+    //both sides edit their message
+    simulation.frontend(1).send(EditMessage(
+        20,
+        2,
+        1,
+        TextMessage("message 1, edited".to_string()),
+    ));
+    expect_edit(&mut simulation, 2, 1, 1);
+    simulation.frontend(2).send(EditMessage(
+        20,
+        1,
+        2,
+        TextMessage("message 2, edited".to_string()),
+    ));
+    expect_edit(&mut simulation, 1, 2, 2);
+
+    //a message arriving while no frontend is attached is not lost
+    simulation.disconnect_frontend(1);
+    send_text(&mut simulation, 2, 20, 1, 3);
+    expect_status(&mut simulation, 2, 3, ReceivedByServer);
+
+    simulation.attach_frontend(1);
+    simulation.expect(
+        1,
+        "the client name",
+        |c| matches!(c, UpdateName(n) if n == "client_1"),
+    );
+    simulation.expect(1, "the room", |c| {
+        matches!(c, UpdateChatRoom(20, Some(true), Some(true)))
+    });
+    simulation.expect(1, "the peer", |c| matches!(c, UpdatePeerName(20, 2, None)));
+    //the status comes first, so the frontend takes the message for ours, not a new one,
+    //then every version, so it is shown as edited with its history
+    expect_status(&mut simulation, 1, 1, ReceivedByPeer);
+    expect_text(&mut simulation, 1, 20, 2, 1);
+    expect_edit(&mut simulation, 1, 2, 1);
+    expect_text(&mut simulation, 1, 20, 2, 2);
+    expect_edit(&mut simulation, 1, 2, 2);
+    expect_text(&mut simulation, 1, 20, 2, 3);
+}
+
+#[test]
+fn route_is_repaired_after_a_link_goes_down() {
+    //the short path 1-5-20 is preferred, 1-6-7-20 is the fallback
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(5, DroneBehaviour::reliable())
+        .drone(6, DroneBehaviour::reliable())
+        .drone(7, DroneBehaviour::reliable())
+        .drone(8, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 5)
+        .link(5, 20)
+        .link(1, 6)
+        .link(6, 7)
+        .link(7, 20)
+        .link(2, 8)
+        .link(8, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    //the client is not told, it finds out from the nack of drone 5
+    simulation.remove_link(5, 20);
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_status(&mut simulation, 1, 1, ReceivedByServer);
+    expect_text(&mut simulation, 2, 20, 1, 1);
+
+    //the only neighbour on the way is gone as well: a new flood is needed
+    simulation.remove_link(1, 6);
+    simulation.add_link(1, 8);
+    send_text(&mut simulation, 1, 20, 2, 2);
+    expect_status(&mut simulation, 1, 2, ReceivedByServer);
+    expect_text(&mut simulation, 2, 20, 1, 2);
+}
+
+#[test]
+fn drone_losing_one_link_keeps_carrying_messages() {
+    //drone 5 is the only neighbour of client 1, it can reach the server directly or through 6
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(5, DroneBehaviour::reliable())
+        .drone(6, DroneBehaviour::reliable())
+        .drone(8, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 5)
+        .link(5, 20)
+        .link(5, 6)
+        .link(6, 20)
+        .link(2, 8)
+        .link(8, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    simulation.remove_link(5, 20);
+    for id in 1..=2 {
+        send_text(&mut simulation, 1, 20, 2, id);
+        expect_status(&mut simulation, 1, id, ReceivedByServer);
+        expect_text(&mut simulation, 2, 20, 1, id);
+    }
+}
+
+#[test]
+fn lost_fragments_are_resent_after_nacks() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    simulation.set_behaviour(11, DroneBehaviour::lossy(0.5));
+    for id in 0..5 {
+        send_text(&mut simulation, 1, 20, 2, id);
+    }
+    //resent fragments may complete the messages in any order
+    let mut received = HashSet::new();
+    while received.len() < 5 {
+        let message = simulation.expect(2, "every message", |c| {
+            matches!(c, UpdateMessageContent(20, 1, _, _, _))
+        });
+        if let UpdateMessageContent(_, _, id, _, _) = message {
+            received.insert(id);
+        }
+    }
+}
+
+#[test]
+fn status_goes_from_failed_to_received() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 11)
+        .link(11, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    //every fragment silently disappears: retransmissions run out
+    simulation.set_behaviour(11, DroneBehaviour::black_hole());
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_status(&mut simulation, 1, 1, FailedToSend);
+    simulation.expect_none(2, "a failed message", Duration::from_secs(1), |c| {
+        matches!(c, UpdateMessageContent(..))
+    });
+
+    //the next message goes through once the drone works again
+    simulation.set_behaviour(11, DroneBehaviour::reliable());
+    send_text(&mut simulation, 1, 20, 2, 2);
+    expect_status(&mut simulation, 1, 2, ReceivedByServer);
+    expect_text(&mut simulation, 2, 20, 1, 2);
+}
+
+#[test]
+fn silent_losses_are_recovered_by_retransmission() {
+    let mut simulation = SimulationBuilder::new()
+        .client(1)
+        .client(2)
+        .drone(11, DroneBehaviour::reliable())
+        .drone(12, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(2, 12)
+        .link(11, 20)
+        .link(12, 20)
+        .build();
+    register(&mut simulation, 1, 20);
+    register(&mut simulation, 2, 20);
+
+    //only the sender's side loses packets, the fake server resends on nacks alone
+    simulation.set_behaviour(
+        11,
+        DroneBehaviour {
+            pdr: 0.4,
+            nack_on_drop: false,
+        },
+    );
+    send_text(&mut simulation, 1, 20, 2, 1);
+    expect_status(&mut simulation, 1, 1, ReceivedByServer);
+    expect_text(&mut simulation, 2, 20, 1, 1);
+}
+
+#[test]
+fn kill_closes_the_frontend_and_stops_the_client() {
+    let mut simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(11, 20)
+        .build();
+    simulation.attach_frontend(1);
+    register(&mut simulation, 1, 20);
+
+    simulation.kill(1);
+    simulation.expect(1, "Kill", |c| matches!(c, TUICommand::Kill));
+    assert!(simulation.frontend(1).closes_within(EXIT_TIMEOUT));
+    assert!(simulation.stops_within(1, EXIT_TIMEOUT));
+    //the listener went with the thread accepting frontends
+    assert!(TcpStream::connect(simulation.listen_address(1)).is_err());
+}
+
+#[test]
+fn dead_frontend_is_let_go_and_can_be_replaced() {
+    let mut simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(11, 20)
+        .build();
+    simulation.attach_frontend(1);
+    register(&mut simulation, 1, 20);
+
+    simulation.frontend(1).send(Dead);
+    assert!(simulation.frontend(1).closes_within(EXIT_TIMEOUT));
+
+    simulation.attach_frontend(1);
+    simulation.expect(1, "the room", |c| {
+        matches!(c, UpdateChatRoom(20, Some(true), Some(true)))
+    });
+}
+
+#[test]
+fn frontend_from_another_build_is_refused() {
+    let simulation = SimulationBuilder::new()
+        .detached_client(1)
+        .drone(11, DroneBehaviour::reliable())
+        .link(1, 11)
+        .build();
+    let mut stream = TcpStream::connect(simulation.listen_address(1)).unwrap();
+    let hello = Hello {
+        protocol_version: PROTOCOL_VERSION + 1,
+        capabilities: vec![],
+        token: None,
+    };
+    send_message(&mut stream, hello).unwrap();
+    let hello = receive_message::<Hello>(&mut stream).unwrap();
+    assert_eq!(hello.protocol_version, PROTOCOL_VERSION);
+
+    //the backend hangs up instead of sending its state
+    assert!(receive_message::<TUICommand>(&mut stream).is_err());
+}
+
+#[test]
+fn frontend_without_the_token_is_refused() {
+    let mut simulation = SimulationBuilder::new()
+        .frontend_token("let me in")
+        .detached_client(1)
+        .drone(11, DroneBehaviour::reliable())
+        .server(20)
+        .link(1, 11)
+        .link(11, 20)
+        .build();
+    let refused = simulation.try_attach_frontend(1, Auth::Open);
+    assert!(matches!(refused, Err(ClientError::HandshakeError(_))));
+    let refused = simulation.try_attach_frontend(1, Auth::Present("let me out"));
+    assert!(matches!(refused, Err(ClientError::HandshakeError(_))));
+
+    simulation.attach_frontend(1);
+    register(&mut simulation, 1, 20);
+}
+
+#[test]
+fn attach_line_is_handed_to_the_embedder_when_no_terminal_opens() {
+    //a terminal set in the environment would be used instead
+    if env::var_os(TERMINAL_ENV).is_some() {
+        return;
+    }
+    let (event_send, _event_recv) = unbounded();
+    let (_controller_send, controller_recv) = unbounded();
+    let (_packet_send, packet_recv) = unbounded();
+    let mut client = Client::new(1, event_send, controller_recv, packet_recv, HashMap::new())
+        .with_frontend_token("secret".to_string())
+        .with_terminal(Terminal::emulator("/nonexistent/terminal", &[]));
+
+    let choice = client.start_frontend().expect("the frontend should start");
+    let Some(LaunchChoice::Print(line)) = choice else {
+        panic!("a terminal was opened: {:?}", choice);
+    };
+    assert!(
+        line.starts_with(&format!("{}=secret ", TOKEN_ENV)),
+        "{}",
+        line
+    );
+    //started once, run() will not open another terminal
+    assert_eq!(client.start_frontend().unwrap(), None);
+}
+
+//waits for a killed client to stop, well before a frontend's handshake would have timed out
+fn stops_before_handshake_timeout(running: JoinHandle<()>) {
+    let killed = Instant::now();
+    while !running.is_finished() && killed.elapsed() < EXIT_TIMEOUT {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(running.is_finished(), "the client is still running");
+    assert!(killed.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn kill_cuts_a_stalled_handshake_short() {
+    let (listener, connector) = memory_listener();
+    let (event_send, _event_recv) = unbounded();
+    let (controller_send, controller_recv) = unbounded();
+    let (_packet_send, packet_recv) = unbounded();
+    let mut client = Client::new(1, event_send, controller_recv, packet_recv, HashMap::new())
+        .with_frontend(FrontendMode::Listen(Box::new(listener)))
+        .with_frontend_token("secret".to_string());
+    let running = thread::spawn(move || client.run());
+
+    //connects but never says hello, the handshake waits for it
+    let _stalled = connector.connect().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    controller_send.send(LeafCommand::Kill).unwrap();
+    stops_before_handshake_timeout(running);
+    //the acceptor was joined, the listener went away with it
+    assert!(connector.connect().is_err());
+}
+
+#[test]
+fn kill_stops_a_client_whose_frontend_never_says_hello() {
+    let (ours, _stalled) = memory_pair();
+    let (event_send, _event_recv) = unbounded();
+    let (controller_send, controller_recv) = unbounded();
+    let (_packet_send, packet_recv) = unbounded();
+    let mut client = Client::new(1, event_send, controller_recv, packet_recv, HashMap::new())
+        .with_frontend(FrontendMode::Stream(Box::new(ours)));
+    let running = thread::spawn(move || client.run());
+
+    thread::sleep(Duration::from_millis(100));
+    controller_send.send(LeafCommand::Kill).unwrap();
+    stops_before_handshake_timeout(running);
+}
@@ -0,0 +1,51 @@
+use client_lib::ClientError;
+use client_lib::ClientError::ListenerError;
+use client_tui::cli::{parse_args, CliArgs, CliCommand, USAGE};
+use client_tui::config::TuiConfig;
+use client_tui::logging::log_to_file;
+use client_tui::loop_forever_chat_tui_over;
+use std::env;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let command = match parse_args(env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("chat-tui: {}\n\n{}", e, USAGE);
+            return ExitCode::from(e.exit_code());
+        }
+    };
+    let args = match command {
+        CliCommand::Help => {
+            print!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        CliCommand::Version => {
+            println!("chat-tui {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        CliCommand::Run(args) => args,
+    };
+    match run(args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{}", e);
+            eprintln!("chat-tui: {}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run(args: CliArgs) -> Result<(), ClientError> {
+    if let Some(log_file) = &args.log_file {
+        log_to_file(log_file)?;
+    }
+    let config = match &args.config {
+        Some(path) => TuiConfig::load(path)?,
+        None => TuiConfig::default(),
+    };
+    let token = args.token()?;
+    log::info!("connecting to {}", args.endpoint);
+    let stream = args.endpoint.connect().map_err(|_| ListenerError)?;
+    loop_forever_chat_tui_over(stream, token.as_deref(), &config)
+}
@@ -0,0 +1,123 @@
+use client_lib::auth::TOKEN_ENV;
+use client_lib::transport::Endpoint;
+use client_lib::ClientError;
+use client_lib::ClientError::{Io, UsageError};
+use std::env;
+use std::fs;
+use std::io::stdin;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: chat-tui [OPTIONS] [ENDPOINT]
+
+Connects to a client backend and shows its chats.
+
+ENDPOINT is a port on localhost, tcp:<address> or unix:<path>.
+
+Options:
+  -p, --port <PORT>         connect to the backend on this localhost port
+  -s, --socket <PATH>       connect to the backend on this unix socket
+      --token-file <PATH>   read the auth token from this file, - for stdin
+                            (by default it is taken from $PICTORUST_TOKEN)
+      --log <PATH>          append log messages to this file
+  -c, --config <PATH>       read the theme from this config file
+  -h, --help                print this help
+  -V, --version             print the version
+
+Exit codes:
+  0 success, 1 internal error, 2 bad arguments or config,
+  3 connection failed or lost, 4 protocol error, 5 token refused, 6 terminal error
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    Run(CliArgs),
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub endpoint: Endpoint,
+    //"-" is stdin
+    pub token_file: Option<PathBuf>,
+    pub log_file: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+}
+
+impl CliArgs {
+    //the token from the file given, or from the environment the backend left it in
+    pub fn token(&self) -> Result<Option<String>, ClientError> {
+        let token = match &self.token_file {
+            Some(path) if path.as_os_str() == "-" => {
+                let mut line = String::new();
+                stdin().read_line(&mut line).map_err(Io)?;
+                line
+            }
+            Some(path) => fs::read_to_string(path).map_err(Io)?,
+            None => env::var(TOKEN_ENV).unwrap_or_default(),
+        };
+        let token = token.trim();
+        Ok((!token.is_empty()).then(|| token.to_string()))
+    }
+}
+
+//args without the program name
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<CliCommand, ClientError> {
+    let mut endpoints = Vec::new();
+    let mut token_file = None;
+    let mut log_file = None;
+    let mut config = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        //"--option=value" is the same as "--option value"
+        let (option, inline) = match arg.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| UsageError(format!("{} needs a value", name)))
+        };
+        match option.as_str() {
+            "-h" | "--help" => return Ok(CliCommand::Help),
+            "-V" | "--version" => return Ok(CliCommand::Version),
+            "-p" | "--port" => {
+                let port = value(&option)?;
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| UsageError(format!("invalid port {}", port)))?;
+                endpoints.push(Endpoint::Tcp(SocketAddr::from(([127, 0, 0, 1], port))));
+            }
+            "-s" | "--socket" => {
+                let path = value(&option)?;
+                endpoints.push(format!("unix:{}", path).parse()?);
+            }
+            "--token-file" => token_file = Some(PathBuf::from(value(&option)?)),
+            "--log" => log_file = Some(PathBuf::from(value(&option)?)),
+            "-c" | "--config" => config = Some(PathBuf::from(value(&option)?)),
+            _ if option.starts_with('-') && option.len() > 1 => {
+                return Err(UsageError(format!("unknown option {}", option)));
+            }
+            _ => endpoints.push(arg.parse()?),
+        }
+    }
+
+    let endpoint = match endpoints.len() {
+        0 => return Err(UsageError("no backend to connect to".to_string())),
+        1 => endpoints.remove(0),
+        _ => return Err(UsageError("more than one backend given".to_string())),
+    };
+    Ok(CliCommand::Run(CliArgs {
+        endpoint,
+        token_file,
+        log_file,
+        config,
+    }))
+}
@@ -4,21 +4,20 @@ use client_lib::communication::MessageContent::TextMessage;
 use client_lib::communication::TUICommand::*;
 use client_lib::communication::TUIEvent::ReadMessage;
 use client_lib::communication::{
-    receive_message, send_message, ChatClientID, ChatServerID, MessageContent, MessageID,
-    MessageStatus, Reaction, TUICommand,
+    ChatClientID, ChatServerID, Connection, MessageContent, MessageID, MessageStatus, Reaction,
+    TUICommand, TimeStamp,
 };
 use client_lib::ClientError;
-use client_lib::ClientError::{LockError, TUICommandHandlingError};
+use client_lib::ClientError::{ConnectionClosed, Decode, LockError, TUICommandHandlingError};
 use std::cell::{RefCell, RefMut};
-use std::net::TcpStream;
 use std::sync::{Arc, Mutex};
 
 pub(crate) fn backend_command_receiver(
     state: Arc<Mutex<RefCell<TUIState>>>,
-    mut stream: TcpStream,
+    mut stream: Connection,
 ) {
     loop {
-        match receive_message::<TUICommand>(&mut stream) {
+        match stream.receive::<TUICommand>() {
             Ok(command) => {
                 //println!("Received command: {:?}", command);
                 match handle_backend_command(&state, &mut stream, command) {
@@ -33,8 +32,18 @@ pub(crate) fn backend_command_receiver(
                     }
                 };
             }
-            Err(_) => {
-                //println!("Error reading command: {:#?}\n\n", e);
+            //a single bad frame is skipped, the stream is still in sync
+            Err(Decode(e)) => log::warn!("skipped a command: {}", e),
+            Err(e) => {
+                let reason = match e {
+                    ConnectionClosed => "The backend exited.".to_string(),
+                    e => format!("Connection to the backend lost: {}", e),
+                };
+                log::warn!("{}", reason);
+                if let Ok(state) = state.lock() {
+                    state.borrow_mut().backend_gone = Some(reason);
+                }
+                break;
             }
         }
     }
@@ -42,7 +51,7 @@ pub(crate) fn backend_command_receiver(
 
 fn handle_backend_command(
     state: &Arc<Mutex<RefCell<TUIState>>>,
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     command: TUICommand,
 ) -> Result<(), ClientError> {
     let state = state.lock().map_err(|_| LockError)?;
@@ -59,14 +68,14 @@ fn handle_backend_command(
         UpdatePeerName(room_id, log_id, name) => {
             handle_peer_name_update(state, room_id, log_id, name)?;
         }
-        UpdatePeerLastSeen(room_id, log_id) => {
-            handle_peer_last_seen_update(state, room_id, log_id)?;
+        UpdatePeerLastSeen(room_id, log_id, time) => {
+            handle_peer_last_seen_update(state, room_id, log_id, time)?;
         }
         UpdatePeerStatus(room_id, log_id, status) => {
             handle_peer_status_update(state, room_id, log_id, status)?;
         }
-        UpdateMessageContent(room_id, log_id, msg_id, content) => {
-            handle_message_content_update(state, stream, room_id, log_id, msg_id, content)?;
+        UpdateMessageContent(room_id, log_id, msg_id, content, time) => {
+            handle_message_content_update(state, stream, room_id, log_id, msg_id, content, time)?;
         }
         UpdateMessageStatus(room_id, log_id, msg_id, reachable) => {
             handle_message_status_update(state, room_id, log_id, msg_id, reachable)?;
@@ -90,12 +99,9 @@ fn handle_chat_room_update(
     registered: Option<bool>,
     reachable: Option<bool>,
 ) -> Result<(), ClientError> {
-    let room_pos_result = state
-        .chat_data
-        .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&id));
+    let room_pos_result = state.chat_data.chat_rooms.iter().position(|cr| cr.id == id);
     match room_pos_result {
-        Ok(room_pos) => {
+        Some(room_pos) => {
             let old_room = &mut state.chat_data.chat_rooms[room_pos];
             if let Some(b) = registered {
                 old_room.registered_to = b;
@@ -104,7 +110,7 @@ fn handle_chat_room_update(
                 old_room.net_reachable = b;
             }
         }
-        Err(_) => {
+        None => {
             let registered = registered.unwrap_or_default();
             let reachable = reachable.unwrap_or_default();
             let new_room = ChatRoom {
@@ -133,25 +139,25 @@ fn handle_peer_name_update(
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
         match log_pos_result {
-            Ok(log_pos) => {
+            Some(log_pos) => {
                 if let Some(name) = name {
                     let log = &mut room.chats[log_pos];
                     log.peer_name = name;
                 }
             }
-            Err(_) => {
+            None => {
                 let log = ChatLog {
                     id: log_id,
                     messages: Vec::new(),
-                    peer_name: format!("Client_{}", log_id),
-
-                    //TODO: set last seen to now
+                    //the backend's default, until the peer says otherwise
+                    peer_name: name.unwrap_or_else(|| format!("client_{}", log_id)),
                     last_seen: 0,
                     currently_creating: TextMessage("".to_string()),
                     pending: 0,
@@ -169,19 +175,20 @@ fn handle_peer_last_seen_update(
     mut state: RefMut<TUIState>,
     room_id: ChatServerID,
     log_id: ChatClientID,
+    time: TimeStamp,
 ) -> Result<(), ClientError> {
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
-            //TODO: set last seen to now
-            log.last_seen += 100;
+            log.last_seen = log.last_seen.max(time);
             return Ok(());
         }
     }
@@ -197,12 +204,13 @@ fn handle_peer_status_update(
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
             log.net_reachable = reachable;
             return Ok(());
@@ -213,49 +221,64 @@ fn handle_peer_status_update(
 }
 fn handle_message_content_update(
     mut state: RefMut<TUIState>,
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     room_id: ChatServerID,
     log_id: ChatClientID,
     msg_id: MessageID,
     content: MessageContent,
+    time: TimeStamp,
 ) -> Result<(), ClientError> {
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
-            let msg_pos_result = log.messages.binary_search_by(|msg| msg.id.cmp(&msg_id));
+            let msg_pos_result = log.messages.iter().position(|msg| msg.id == msg_id);
+            let mut incoming = true;
             match msg_pos_result {
-                Ok(msg_pos) => {
+                Some(msg_pos) => {
                     let msg = &mut log.messages[msg_pos];
-                    msg.content = Some(content);
-                    msg.edited = true;
+                    //deleted messages stay deleted; the backend turns away peers
+                    //editing our messages, so these are ours only when replayed
+                    if msg.deleted || msg.content.as_ref() == Some(&content) {
+                        return Ok(());
+                    }
+                    incoming = msg.status.is_none();
+                    match msg.content.replace(content) {
+                        Some(old) => {
+                            msg.history.push(old);
+                            msg.edited = true;
+                        }
+                        //a message of ours whose status came first
+                        None => msg.timestamp = time,
+                    }
                 }
-                Err(_) => {
+                None => {
                     log.messages.push(ChatMessage {
                         id: msg_id,
                         content: Some(content),
-                        //TODO set ts to now
-                        timestamp: 0,
+                        timestamp: time,
                         status: None,
                         reaction: None,
                         edited: false,
                         deleted: false,
+                        history: vec![],
                     });
                     room.pending += 1;
                     log.pending += 1;
                 }
             }
             match state.ui_data.current_room {
-                Some(r) if state.chat_data.chat_rooms[r].id == room_id => {
+                Some(r) if incoming && state.chat_data.chat_rooms[r].id == room_id => {
                     match state.ui_data.current_log {
                         Some(l) if state.chat_data.chat_rooms[r].chats[l].id == log_id => {
-                            send_message(stream, ReadMessage(room_id, log_id, msg_id))?;
+                            stream.send(ReadMessage(room_id, log_id, msg_id))?;
                         }
                         _ => {}
                     };
@@ -278,19 +301,35 @@ fn handle_message_status_update(
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
-            let msg_pos_result = log.messages.binary_search_by(|msg| msg.id.cmp(&msg_id));
-            if let Ok(msg_pos) = msg_pos_result {
-                let msg = &mut log.messages[msg_pos];
-                msg.status = Some(status);
-                return Ok(());
+            let msg_pos_result = log.messages.iter().position(|msg| msg.id == msg_id);
+            match msg_pos_result {
+                Some(msg_pos) => {
+                    let msg = &mut log.messages[msg_pos];
+                    if status.replaces(msg.status) {
+                        msg.status = Some(status);
+                    }
+                }
+                //a message of ours replayed to a new frontend, its content follows
+                None => log.messages.push(ChatMessage {
+                    id: msg_id,
+                    content: None,
+                    timestamp: 0,
+                    status: Some(status),
+                    reaction: None,
+                    edited: false,
+                    deleted: false,
+                    history: vec![],
+                }),
             }
+            return Ok(());
         }
     }
 
@@ -306,15 +345,16 @@ fn handle_message_reaction_update(
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
-            let msg_pos_result = log.messages.binary_search_by(|msg| msg.id.cmp(&msg_id));
-            if let Ok(msg_pos) = msg_pos_result {
+            let msg_pos_result = log.messages.iter().position(|msg| msg.id == msg_id);
+            if let Some(msg_pos) = msg_pos_result {
                 let msg = &mut log.messages[msg_pos];
                 msg.reaction = reaction;
                 return Ok(());
@@ -333,19 +373,21 @@ fn handle_message_delete(
     let room_pos_result = state
         .chat_data
         .chat_rooms
-        .binary_search_by(|cr| cr.id.cmp(&room_id));
+        .iter()
+        .position(|cr| cr.id == room_id);
 
-    if let Ok(room_pos) = room_pos_result {
+    if let Some(room_pos) = room_pos_result {
         let room = &mut state.chat_data.chat_rooms[room_pos];
-        let log_pos_result = room.chats.binary_search_by(|cl| cl.id.cmp(&log_id));
-        if let Ok(log_pos) = log_pos_result {
+        let log_pos_result = room.chats.iter().position(|cl| cl.id == log_id);
+        if let Some(log_pos) = log_pos_result {
             let log = &mut room.chats[log_pos];
-            let msg_pos_result = log.messages.binary_search_by(|msg| msg.id.cmp(&msg_id));
-            if let Ok(msg_pos) = msg_pos_result {
+            let msg_pos_result = log.messages.iter().position(|msg| msg.id == msg_id);
+            if let Some(msg_pos) = msg_pos_result {
                 let msg = &mut log.messages[msg_pos];
                 msg.deleted = true;
                 msg.content = None;
                 msg.reaction = None;
+                msg.history.clear();
                 msg.status = None;
                 return Ok(());
             }
@@ -354,3 +396,125 @@ fn handle_message_delete(
 
     Err(TUICommandHandlingError)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Theme;
+    use client_lib::auth::Auth;
+    use client_lib::communication::handshake;
+    use client_lib::transport::memory_pair;
+    use std::thread;
+
+    //a backend link nobody reads from, it is only written to for read receipts
+    fn connection() -> (Connection, Connection) {
+        let (ours, theirs) = memory_pair();
+        let backend = thread::spawn(move || handshake(Box::new(theirs), &[], Auth::Open));
+        let ours = handshake(Box::new(ours), &[], Auth::Open).unwrap();
+        (ours, backend.join().unwrap().unwrap())
+    }
+
+    fn text(text: &str) -> MessageContent {
+        TextMessage(text.to_string())
+    }
+
+    #[test]
+    fn messages_are_found_whatever_their_ids() {
+        let state = Arc::new(Mutex::new(RefCell::new(TUIState::new(Theme::default()))));
+        let (mut stream, _backend) = connection();
+        //rooms, peers and message ids arrive in no particular order
+        let commands = vec![
+            UpdateChatRoom(30, Some(true), Some(true)),
+            UpdateChatRoom(20, Some(true), Some(true)),
+            UpdatePeerName(20, 5, None),
+            UpdatePeerName(20, 2, None),
+            UpdateMessageContent(20, 2, 900, text("first"), 1),
+            UpdateMessageContent(20, 2, 100, text("second"), 2),
+            UpdateMessageContent(20, 2, 500, text("third"), 3),
+            UpdateMessageContent(20, 2, 900, text("first, edited"), 4),
+            UpdateMessageReaction(20, 2, 100, Some(Reaction::Heart)),
+            UpdateMessageStatus(20, 2, 100, MessageStatus::ReadByPeer),
+            DeleteMessage(20, 2, 500),
+        ];
+        for command in commands {
+            handle_backend_command(&state, &mut stream, command).unwrap();
+        }
+
+        let state = state.lock().unwrap();
+        let state = state.borrow();
+        assert_eq!(state.chat_data.chat_rooms.len(), 2);
+        let room = &state.chat_data.chat_rooms[1];
+        assert_eq!(room.chats.len(), 2);
+        assert_eq!(room.pending, 3);
+        let log = &room.chats[1];
+        assert_eq!(log.pending, 3);
+
+        let ids = log.messages.iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![900, 100, 500]);
+        let edited = &log.messages[0];
+        assert_eq!(edited.content, Some(text("first, edited")));
+        assert_eq!(edited.history, vec![text("first")]);
+        assert!(edited.edited);
+        assert_eq!(log.messages[1].reaction, Some(Reaction::Heart));
+        assert_eq!(log.messages[1].status, Some(MessageStatus::ReadByPeer));
+        assert!(log.messages[2].deleted);
+    }
+
+    #[test]
+    fn a_name_arriving_first_names_the_new_peer() {
+        let state = Arc::new(Mutex::new(RefCell::new(TUIState::new(Theme::default()))));
+        let (mut stream, _backend) = connection();
+        let commands = vec![
+            UpdateChatRoom(20, Some(true), Some(true)),
+            UpdatePeerName(20, 2, Some("alice".to_string())),
+            UpdatePeerName(20, 5, None),
+        ];
+        for command in commands {
+            handle_backend_command(&state, &mut stream, command).unwrap();
+        }
+
+        let state = state.lock().unwrap();
+        let state = state.borrow();
+        let names = state.chat_data.chat_rooms[0]
+            .chats
+            .iter()
+            .map(|log| log.peer_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["alice", "client_5"]);
+    }
+
+    #[test]
+    fn replayed_messages_of_ours_are_not_unread_and_keep_their_edits() {
+        let state = Arc::new(Mutex::new(RefCell::new(TUIState::new(Theme::default()))));
+        let (mut stream, _backend) = connection();
+        //what a backend replays to a frontend attaching late
+        let commands = vec![
+            UpdateChatRoom(20, Some(true), Some(true)),
+            UpdatePeerName(20, 2, None),
+            UpdateMessageStatus(20, 2, 1, MessageStatus::ReceivedByPeer),
+            UpdateMessageContent(20, 2, 1, text("mine"), 10),
+            UpdateMessageContent(20, 2, 1, text("mine, edited"), 10),
+            UpdateMessageContent(20, 2, 2, text("theirs"), 20),
+            UpdateMessageContent(20, 2, 2, text("theirs, edited"), 20),
+        ];
+        for command in commands {
+            handle_backend_command(&state, &mut stream, command).unwrap();
+        }
+
+        let state = state.lock().unwrap();
+        let state = state.borrow();
+        let room = &state.chat_data.chat_rooms[0];
+        let log = &room.chats[0];
+        assert_eq!((room.pending, log.pending), (1, 1));
+
+        let mine = &log.messages[0];
+        assert_eq!(mine.status, Some(MessageStatus::ReceivedByPeer));
+        assert_eq!(mine.timestamp, 10);
+        assert_eq!(mine.content, Some(text("mine, edited")));
+        assert_eq!(mine.history, vec![text("mine")]);
+        assert!(mine.edited);
+        let theirs = &log.messages[1];
+        assert_eq!(theirs.history, vec![text("theirs")]);
+        assert!(theirs.edited);
+    }
+}
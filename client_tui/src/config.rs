@@ -0,0 +1,81 @@
+use client_lib::ClientError;
+use client_lib::ClientError::{InvalidConfig, Io};
+use ratatui::style::Color;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+//colors used by the TUI, the defaults are the original look
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Theme {
+    //borders of the active component, selected rows and headers
+    pub accent: Color,
+    //second header column
+    pub accent_light: Color,
+    //unread counters
+    pub pending: Color,
+    //everything is fine: reachable, registered, nothing unread
+    pub ok: Color,
+    //unreachable, not registered, alerts
+    pub error: Color,
+    pub help: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            accent: Color::Green,
+            accent_light: Color::LightGreen,
+            pending: Color::Yellow,
+            ok: Color::Green,
+            error: Color::Red,
+            help: Color::Magenta,
+        }
+    }
+}
+
+//settings read from the config file given to chat-tui
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TuiConfig {
+    pub theme: Theme,
+}
+
+impl TuiConfig {
+    pub fn load(path: &Path) -> Result<Self, ClientError> {
+        let text = fs::read_to_string(path).map_err(Io)?;
+        text.parse()
+    }
+}
+
+//one "key = value" per line, lines starting with '#' are comments, colors are names or #rrggbb
+impl FromStr for TuiConfig {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = TuiConfig::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |why: &str| InvalidConfig(format!("line {}: {}", n + 1, why));
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(invalid("expected key = value"));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let theme = &mut config.theme;
+            let color = match key {
+                "accent" => &mut theme.accent,
+                "accent_light" => &mut theme.accent_light,
+                "pending" => &mut theme.pending,
+                "ok" => &mut theme.ok,
+                "error" => &mut theme.error,
+                "help" => &mut theme.help,
+                _ => return Err(invalid(&format!("unknown key {}", key))),
+            };
+            *color =
+                Color::from_str(value).map_err(|_| invalid(&format!("unknown color {}", value)))?;
+        }
+        Ok(config)
+    }
+}
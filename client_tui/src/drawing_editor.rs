@@ -0,0 +1,222 @@
+use client_lib::communication::{
+    Drawing, SerializableCircle, SerializableColor, SerializableLine, SerializablePoint,
+    SerializableRectangle, SerializableShape,
+};
+use ratatui::layout::Rect;
+use std::cell::Cell;
+
+//size of the drawing being composed, a cell is twice as tall as it is wide
+//so this fits a 60x15 area without stretching
+const CANVAS_WIDTH: f64 = 120.0;
+const CANVAS_HEIGHT: f64 = 60.0;
+//how far the cursor moves with the arrows, and with shift held
+const STEP: f64 = 1.0;
+const BIG_STEP: f64 = 5.0;
+
+pub(crate) const PALETTE: [(&str, SerializableColor); 8] = [
+    ("white", SerializableColor::new(255, 255, 255)),
+    ("red", SerializableColor::new(230, 40, 40)),
+    ("green", SerializableColor::new(40, 200, 60)),
+    ("blue", SerializableColor::new(50, 110, 240)),
+    ("yellow", SerializableColor::new(240, 220, 40)),
+    ("magenta", SerializableColor::new(220, 60, 220)),
+    ("cyan", SerializableColor::new(40, 210, 220)),
+    ("orange", SerializableColor::new(250, 140, 20)),
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Tool {
+    Point,
+    Line,
+    Rectangle,
+    Circle,
+}
+
+impl Tool {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Tool::Point => "point",
+            Tool::Line => "line",
+            Tool::Rectangle => "rectangle",
+            Tool::Circle => "circle",
+        }
+    }
+}
+
+//the drawing composer: points are placed at once, the other shapes
+//go from the anchor set by the first placement to the cursor
+#[derive(Debug, Clone)]
+pub(crate) struct DrawingEditor {
+    pub drawing: Drawing,
+    pub tool: Tool,
+    //index in PALETTE
+    pub color: usize,
+    pub cursor: (f64, f64),
+    pub anchor: Option<(f64, f64)>,
+    //where the canvas was drawn last, to turn mouse positions into coordinates
+    pub canvas_area: Cell<Rect>,
+}
+
+impl DrawingEditor {
+    pub(crate) fn new() -> Self {
+        DrawingEditor {
+            drawing: Drawing::new(),
+            tool: Tool::Point,
+            color: 0,
+            cursor: (CANVAS_WIDTH / 2.0, CANVAS_HEIGHT / 2.0),
+            anchor: None,
+            canvas_area: Cell::new(Rect::default()),
+        }
+    }
+
+    pub(crate) fn color(&self) -> &SerializableColor {
+        &PALETTE[self.color].1
+    }
+
+    pub(crate) fn next_color(&mut self) {
+        self.color = (self.color + 1) % PALETTE.len();
+    }
+
+    pub(crate) fn select_color(&mut self, index: usize) {
+        if index < PALETTE.len() {
+            self.color = index;
+        }
+    }
+
+    pub(crate) fn select_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+        self.anchor = None;
+    }
+
+    //(min x, min y, max x, max y) shown: the canvas, grown to fit a loaded drawing
+    pub(crate) fn view(&self) -> (f64, f64, f64, f64) {
+        let (min_x, min_y, max_x, max_y) = self.drawing.bounds().unwrap_or((0.0, 0.0, 0.0, 0.0));
+        (
+            min_x.min(0.0),
+            min_y.min(0.0),
+            max_x.max(CANVAS_WIDTH),
+            max_y.max(CANVAS_HEIGHT),
+        )
+    }
+
+    pub(crate) fn move_cursor(&mut self, dx: f64, dy: f64, big: bool) {
+        let step = if big { BIG_STEP } else { STEP };
+        let (min_x, min_y, max_x, max_y) = self.view();
+        self.cursor = (
+            (self.cursor.0 + dx * step).clamp(min_x, max_x),
+            (self.cursor.1 + dy * step).clamp(min_y, max_y),
+        );
+    }
+
+    //moves the cursor under a terminal cell, false if the cell is outside the canvas
+    pub(crate) fn point_cursor_at(&mut self, column: u16, row: u16) -> bool {
+        let area = self.canvas_area.get();
+        if area.width == 0
+            || area.height == 0
+            || column < area.x
+            || row < area.y
+            || column >= area.x + area.width
+            || row >= area.y + area.height
+        {
+            return false;
+        }
+        let (min_x, min_y, max_x, max_y) = self.view();
+        self.cursor = (
+            min_x + ((column - area.x) as f64 + 0.5) * (max_x - min_x) / area.width as f64,
+            min_y + ((row - area.y) as f64 + 0.5) * (max_y - min_y) / area.height as f64,
+        );
+        true
+    }
+
+    //places a point, or starts or ends the current shape
+    pub(crate) fn place(&mut self) {
+        if self.tool == Tool::Point {
+            let shape = self.shape_to(self.cursor, self.cursor);
+            self.drawing.push(shape);
+            return;
+        }
+        match self.anchor.take() {
+            None => self.anchor = Some(self.cursor),
+            Some(anchor) => {
+                let shape = self.shape_to(anchor, self.cursor);
+                self.drawing.push(shape);
+            }
+        }
+    }
+
+    //a click places like the keyboard does
+    pub(crate) fn press(&mut self, column: u16, row: u16) {
+        if self.point_cursor_at(column, row) {
+            self.place();
+        }
+    }
+
+    //ends a dragged shape, after a plain click the shape stays open for a second click
+    pub(crate) fn release(&mut self, column: u16, row: u16) {
+        if let Some(anchor) = self.anchor {
+            if self.point_cursor_at(column, row) && self.cursor != anchor {
+                self.place();
+            }
+        }
+    }
+
+    //the shape that placing now would add, shown while it is drawn
+    pub(crate) fn preview(&self) -> Option<SerializableShape> {
+        self.anchor.map(|anchor| self.shape_to(anchor, self.cursor))
+    }
+
+    //drops the shape being drawn, or else the last one placed
+    pub(crate) fn undo(&mut self) {
+        if self.anchor.take().is_none() {
+            self.drawing.pop();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.anchor = None;
+        self.drawing.clear();
+    }
+
+    //replaces what is being composed, e.g. with a drawing read from a file
+    pub(crate) fn load(&mut self, drawing: Drawing) {
+        self.anchor = None;
+        self.drawing = drawing;
+    }
+
+    //the finished drawing, leaving the editor empty
+    pub(crate) fn take(&mut self) -> Drawing {
+        self.anchor = None;
+        std::mem::take(&mut self.drawing)
+    }
+
+    fn shape_to(&self, (x1, y1): (f64, f64), (x2, y2): (f64, f64)) -> SerializableShape {
+        let color = self.color().clone();
+        match self.tool {
+            Tool::Point => SerializableShape::Point(SerializablePoint {
+                x: x2,
+                y: y2,
+                color,
+            }),
+            Tool::Line => SerializableShape::Line(SerializableLine {
+                x1,
+                y1,
+                x2,
+                y2,
+                color,
+            }),
+            Tool::Rectangle => SerializableShape::Rectangle(SerializableRectangle {
+                x: x1.min(x2),
+                y: y1.min(y2),
+                width: (x2 - x1).abs(),
+                height: (y2 - y1).abs(),
+                color,
+            }),
+            Tool::Circle => SerializableShape::Circle(SerializableCircle {
+                x: x1,
+                y: y1,
+                radius: (x2 - x1).hypot(y2 - y1),
+                color,
+            }),
+        }
+    }
+}
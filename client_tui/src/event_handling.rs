@@ -1,24 +1,39 @@
+use crate::drawing_editor::Tool;
+use crate::files::{default_path, load_drawing, save_message};
 use crate::state::ActiveComponent::*;
 use crate::state::NameSetAction::*;
-use crate::state::{ChatMessage, TUIState};
-use client_lib::communication::MessageContent::TextMessage;
+use crate::state::{ChatMessage, FileAction, TUIState};
+use crate::ui::ui_utils::REACTIONS;
+use client_lib::communication::MessageContent;
+use client_lib::communication::MessageContent::{Drawing, TextMessage};
 use client_lib::communication::MessageStatus::SentToServer;
 use client_lib::communication::TUIEvent::{
-    DeleteMessage, RegisterToServer, RequestRoomList, SendMessage, SetName,
+    DeleteMessage, EditMessage, ReactToMessage, RegisterToServer, RequestRoomList, SendMessage,
+    SetName,
 };
-use client_lib::communication::{send_message, ChatClientID, ChatServerID};
+use client_lib::communication::{now, ChatClientID, ChatServerID, Connection};
 use client_lib::ClientError;
 use rand::Rng;
 use ratatui::crossterm::event;
-use ratatui::crossterm::event::{Event, KeyCode, KeyModifiers};
+use ratatui::crossterm::event::{Event, KeyCode, KeyModifiers, MouseButton, MouseEventKind};
 use std::cell::RefMut;
-use std::net::TcpStream;
+use std::path::Path;
 
 pub(super) fn handle_event(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     mut state: RefMut<TUIState>,
     event: Event,
 ) -> Result<(), ClientError> {
+    if let Event::Key(key) = event {
+        if key.kind == event::KeyEventKind::Press {
+            state.ui_data.notice = None;
+            //quits from anywhere, no editor uses it
+            if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('q') {
+                state.kill = true;
+                return Ok(());
+            }
+        }
+    }
     match &state.ui_data.active_component {
         NameSet(action) => match action {
             Displaying => {
@@ -32,13 +47,20 @@ pub(super) fn handle_event(
         ChatSelect => handle_chat_select_event(&mut state, event)?,
         ChatView => handle_chat_view_event(stream, &mut state, event)?,
         TextEdit => handle_text_area_event(stream, &mut state, event)?,
+        DrawingEdit => handle_drawing_edit_event(stream, &mut state, event)?,
+        ReactionSend => handle_reaction_send_event(stream, &mut state, event)?,
+        EditHistory => handle_edit_history_event(&mut state, event)?,
+        FilePrompt(action) => {
+            let action = action.clone();
+            handle_file_prompt_event(&mut state, action, event)?;
+        }
         _ => {}
     }
     Ok(())
 }
 
 fn handle_name_set_changing_event(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
     event: Event,
 ) -> Result<(), ClientError> {
@@ -54,7 +76,7 @@ fn handle_name_set_changing_event(
             KeyCode::Enter => {
                 if let Some(name) = &state.ui_data.name_in_editing {
                     if !name.is_empty() {
-                        send_message(stream, SetName(name.clone()))?;
+                        stream.send(SetName(name.clone()))?;
                         state.ui_data.name_in_editing = None;
                         state.ui_data.active_component = NameSet(Displaying);
                     }
@@ -109,7 +131,7 @@ fn handle_name_set_displaying_event(
     Ok(())
 }
 fn handle_room_select_event(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
     event: Event,
 ) -> Result<(), ClientError> {
@@ -139,7 +161,7 @@ fn handle_room_select_event(
                 KeyCode::Enter => {
                     if let Some(r_id) = state.ui_data.selected_room {
                         let room = &state.chat_data.chat_rooms[r_id];
-                        let _ = send_message(stream, RequestRoomList(room.id));
+                        let _ = stream.send(RequestRoomList(room.id));
                         if room.registered_to {
                             if let Some(curr_room_id) = &state.ui_data.current_room {
                                 if r_id != *curr_room_id {
@@ -158,7 +180,7 @@ fn handle_room_select_event(
                     if let Some(r_id) = state.ui_data.selected_room {
                         let room = &state.chat_data.chat_rooms[r_id];
                         if !room.registered_to && room.net_reachable {
-                            send_message(stream, RegisterToServer(room.id))?;
+                            stream.send(RegisterToServer(room.id))?;
                         }
                     }
                 }
@@ -223,7 +245,7 @@ fn handle_chat_select_event(state: &mut RefMut<TUIState>, event: Event) -> Resul
 }
 
 fn handle_chat_view_event(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
     event: Event,
 ) -> Result<(), ClientError> {
@@ -266,6 +288,18 @@ fn handle_chat_view_event(
                 KeyCode::Char('d') => {
                     delete_selected_message(stream, state)?;
                 }
+                KeyCode::Char('s') => {
+                    open_save_prompt(state);
+                }
+                KeyCode::Char('r') => {
+                    open_reaction_picker(state);
+                }
+                KeyCode::Char('e') => {
+                    start_editing_selected_message(state);
+                }
+                KeyCode::Char('h') => {
+                    open_edit_history(state);
+                }
                 _ => {}
             }
         }
@@ -274,11 +308,10 @@ fn handle_chat_view_event(
 }
 
 fn handle_text_area_event(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
     event: Event,
 ) -> Result<(), ClientError> {
-    let text = &mut state.ui_data.text_message_in_edit;
     if let Event::Key(key) = event {
         if key.kind == event::KeyEventKind::Release {
             return Ok(());
@@ -286,18 +319,30 @@ fn handle_text_area_event(
         if key.modifiers.contains(KeyModifiers::CONTROL) {
             match key.code {
                 KeyCode::Left => {
+                    stop_editing(state);
                     go_to_chat_select(state);
                 }
                 KeyCode::Up => {
+                    stop_editing(state);
                     go_to_chat_view(state);
                 }
                 KeyCode::Char('s') => {
+                    let editing = state.ui_data.editing.is_some();
                     send_current_text_message(stream, state)?;
-                    select_last_message(state)?;
+                    if !editing {
+                        select_last_message(state)?;
+                    }
+                }
+                KeyCode::Char('d') => {
+                    go_to_drawing_edit(state);
                 }
                 _ => {}
             }
+        } else if key.code == KeyCode::Esc && state.ui_data.editing.is_some() {
+            stop_editing(state);
+            go_to_chat_view(state);
         } else {
+            let text = &mut state.ui_data.text_message_in_edit;
             match key.code {
                 KeyCode::Char(c) => {
                     text.push(c);
@@ -324,10 +369,228 @@ fn handle_text_area_event(
     Ok(())
 }
 
+fn handle_drawing_edit_event(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+    event: Event,
+) -> Result<(), ClientError> {
+    match event {
+        Event::Key(key) => {
+            if key.kind == event::KeyEventKind::Release {
+                return Ok(());
+            }
+            if key.modifiers.contains(KeyModifiers::CONTROL) {
+                match key.code {
+                    KeyCode::Left => {
+                        stop_editing(state);
+                        go_to_chat_select(state);
+                    }
+                    KeyCode::Up => {
+                        stop_editing(state);
+                        go_to_chat_view(state);
+                    }
+                    //an edit stays in the editor of its kind
+                    KeyCode::Char('d') if state.ui_data.editing.is_none() => {
+                        go_to_text_area(state);
+                    }
+                    KeyCode::Char('s') => {
+                        let editing = state.ui_data.editing.is_some();
+                        send_current_drawing(stream, state)?;
+                        if !editing {
+                            select_last_message(state)?;
+                        }
+                    }
+                    KeyCode::Char('z') => {
+                        state.ui_data.drawing_in_edit.undo();
+                    }
+                    _ => {}
+                }
+            } else {
+                //shift moves the cursor faster
+                let big = key.modifiers.contains(KeyModifiers::SHIFT);
+                let editor = &mut state.ui_data.drawing_in_edit;
+                match key.code {
+                    KeyCode::Up => editor.move_cursor(0.0, -1.0, big),
+                    KeyCode::Down => editor.move_cursor(0.0, 1.0, big),
+                    KeyCode::Left => editor.move_cursor(-1.0, 0.0, big),
+                    KeyCode::Right => editor.move_cursor(1.0, 0.0, big),
+                    KeyCode::Enter | KeyCode::Char(' ') => editor.place(),
+                    KeyCode::Char('p') | KeyCode::Char('P') => editor.select_tool(Tool::Point),
+                    KeyCode::Char('l') | KeyCode::Char('L') => editor.select_tool(Tool::Line),
+                    KeyCode::Char('r') | KeyCode::Char('R') => editor.select_tool(Tool::Rectangle),
+                    KeyCode::Char('c') | KeyCode::Char('C') => editor.select_tool(Tool::Circle),
+                    KeyCode::Tab => editor.next_color(),
+                    KeyCode::Char(c @ '1'..='8') => editor.select_color(c as usize - '1' as usize),
+                    KeyCode::Char('u') | KeyCode::Char('U') => editor.undo(),
+                    KeyCode::Char('x') | KeyCode::Char('X') => editor.clear(),
+                    KeyCode::Char('o') | KeyCode::Char('O') => {
+                        state.ui_data.path_in_editing = "".to_string();
+                        state.ui_data.active_component = FilePrompt(FileAction::LoadDrawing);
+                    }
+                    KeyCode::Esc => {
+                        if editor.anchor.is_some() {
+                            editor.anchor = None;
+                        } else if state.ui_data.editing.is_some() {
+                            stop_editing(state);
+                            go_to_chat_view(state);
+                        } else {
+                            go_to_text_area(state);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Event::Mouse(mouse) => {
+            let editor = &mut state.ui_data.drawing_in_edit;
+            match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    editor.press(mouse.column, mouse.row);
+                }
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    editor.point_cursor_at(mouse.column, mouse.row);
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    editor.release(mouse.column, mouse.row);
+                }
+                MouseEventKind::Down(MouseButton::Right) => {
+                    editor.undo();
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn handle_reaction_send_event(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+    event: Event,
+) -> Result<(), ClientError> {
+    if let Event::Key(key) = event {
+        if key.kind == event::KeyEventKind::Release {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Left => {
+                reaction_select_move(state, false);
+            }
+            KeyCode::Right => {
+                reaction_select_move(state, true);
+            }
+            KeyCode::Enter => {
+                send_selected_reaction(stream, state)?;
+                close_reaction_picker(state);
+            }
+            KeyCode::Esc => {
+                close_reaction_picker(state);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn handle_edit_history_event(
+    state: &mut RefMut<TUIState>,
+    event: Event,
+) -> Result<(), ClientError> {
+    if let Event::Key(key) = event {
+        if key.kind == event::KeyEventKind::Release {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Left => {
+                state.ui_data.history_version = state.ui_data.history_version.saturating_sub(1);
+            }
+            KeyCode::Right => {
+                //the versions are the history and the current content
+                let last = selected_message(state).map_or(0, |m| m.history.len());
+                if state.ui_data.history_version < last {
+                    state.ui_data.history_version += 1;
+                }
+            }
+            KeyCode::Esc | KeyCode::Char('h') => {
+                state.ui_data.active_component = ChatView;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn handle_file_prompt_event(
+    state: &mut RefMut<TUIState>,
+    action: FileAction,
+    event: Event,
+) -> Result<(), ClientError> {
+    if let Event::Key(key) = event {
+        if key.kind == event::KeyEventKind::Release {
+            return Ok(());
+        }
+        let back = match action {
+            FileAction::SaveMessage => ChatView,
+            FileAction::LoadDrawing => DrawingEdit,
+        };
+        match key.code {
+            KeyCode::Esc => {
+                state.ui_data.active_component = back;
+            }
+            KeyCode::Enter => {
+                let path = state.ui_data.path_in_editing.clone();
+                if path.is_empty() {
+                    return Ok(());
+                }
+                let result = match action {
+                    FileAction::SaveMessage => {
+                        match selected_message(state).and_then(|m| m.content) {
+                            Some(content) => save_message(Path::new(&path), &content)
+                                .map(|_| format!("Saved to {}", path)),
+                            //deleted while the path was written
+                            None => Ok("Nothing to save, the message is gone".to_string()),
+                        }
+                    }
+                    FileAction::LoadDrawing => load_drawing(Path::new(&path)).map(|drawing| {
+                        state.ui_data.drawing_in_edit.load(drawing);
+                        format!("Loaded {}", path)
+                    }),
+                };
+                //on failure the prompt stays open to fix the path
+                match result {
+                    Ok(notice) => {
+                        state.ui_data.notice = Some(notice);
+                        state.ui_data.active_component = back;
+                    }
+                    Err(e) => state.ui_data.notice = Some(e.to_string()),
+                }
+            }
+            KeyCode::Char(c) => {
+                state.ui_data.path_in_editing.push(c);
+            }
+            KeyCode::Backspace => {
+                state.ui_data.path_in_editing.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 fn go_to_text_area(state: &mut RefMut<TUIState>) {
     state.ui_data.active_component = TextEdit;
 }
 
+fn go_to_drawing_edit(state: &mut RefMut<TUIState>) {
+    if state.ui_data.drawings_enabled
+        && state.ui_data.current_log.is_some()
+        && state.ui_data.editing.is_none()
+    {
+        state.ui_data.active_component = DrawingEdit;
+    }
+}
+
 fn go_to_room_select(state: &mut RefMut<TUIState>) {
     state.ui_data.active_component = RoomSelect;
     state.ui_data.selected_log = None;
@@ -428,9 +691,83 @@ fn read_log(state: &mut RefMut<TUIState>, room_id: usize, log_id: usize) {
 }
 
 fn send_current_text_message(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+) -> Result<(), ClientError> {
+    let mut msg = state.ui_data.text_message_in_edit.clone();
+    let mut cleaned = false;
+    while !cleaned {
+        if let Some(c) = msg.chars().last() {
+            if c == '\n' || c == ' ' {
+                msg.pop();
+            } else {
+                cleaned = true;
+            }
+        } else {
+            cleaned = true;
+        }
+    }
+    if !msg.is_empty() && send_or_edit(stream, state, TextMessage(msg))? {
+        state.ui_data.text_message_in_edit = "".to_string();
+    }
+    Ok(())
+}
+
+fn send_current_drawing(
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
 ) -> Result<(), ClientError> {
+    let drawing = state.ui_data.drawing_in_edit.drawing.clone();
+    if !drawing.is_empty() && send_or_edit(stream, state, Drawing(drawing))? {
+        state.ui_data.drawing_in_edit.take();
+    }
+    Ok(())
+}
+
+//the editors send an edit while a message is loaded in them, a new message otherwise
+fn send_or_edit(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+    content: MessageContent,
+) -> Result<bool, ClientError> {
+    match state.ui_data.editing {
+        Some(m_id) => edit_message_content(stream, state, m_id, content),
+        None => send_message_content(stream, state, content),
+    }
+}
+
+//replaces the content of one of our messages, keeping the old one in its history;
+//the edit is dropped if the message was deleted in the meantime
+fn edit_message_content(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+    m_id: usize,
+    content: MessageContent,
+) -> Result<bool, ClientError> {
+    if let (Some(r_id), Some(l_id)) = (state.ui_data.current_room, state.ui_data.current_log) {
+        let room = &mut state.chat_data.chat_rooms[r_id];
+        let log = &mut room.chats[l_id];
+        if let Some(msg) = log.messages.get_mut(m_id) {
+            if msg.content.is_some() && msg.content.as_ref() != Some(&content) {
+                stream.send(EditMessage(room.id, log.id, msg.id, content.clone()))?;
+                if let Some(old) = msg.content.replace(content) {
+                    msg.history.push(old);
+                }
+                msg.edited = true;
+            }
+        }
+    }
+    state.ui_data.editing = None;
+    go_to_chat_view(state);
+    Ok(true)
+}
+
+//sends the message to the current chat and adds it to the log, false if no chat is open
+fn send_message_content(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+    content: MessageContent,
+) -> Result<bool, ClientError> {
     if let Some(room_pos) = state.ui_data.current_room {
         if let Some(log_pos) = state.ui_data.current_log {
             let room = &state.chat_data.chat_rooms[room_pos];
@@ -439,49 +776,30 @@ fn send_current_text_message(
             let log_id = log.id;
             let mut rng = rand::rng();
             let msg_id = rng.random();
-            let mut msg = state.ui_data.text_message_in_edit.clone();
-            let mut cleaned = false;
-            while !cleaned {
-                if let Some(c) = msg.chars().last() {
-                    if c == '\n' || c == ' ' {
-                        msg.pop();
-                    } else {
-                        cleaned = true;
-                    }
-                } else {
-                    cleaned = true;
-                }
-            }
-            if !msg.is_empty() {
-                let content = TextMessage(msg);
-                send_message(
-                    stream,
-                    SendMessage(
-                        room_id as ChatServerID,
-                        log_id as ChatClientID,
-                        msg_id,
-                        content.clone(),
-                    ),
-                )?;
-                state.chat_data.chat_rooms[room_pos].chats[log_pos]
-                    .messages
-                    .push(ChatMessage {
-                        id: msg_id,
-                        content: Some(content),
-                        //TODO set to now
-                        timestamp: 0,
-                        status: Some(SentToServer),
-                        reaction: None,
-                        edited: false,
-                        deleted: false,
-                    });
-                state.ui_data.text_message_in_edit = "".to_string();
-                let mut go_to_chat_bottom = state.ui_data.go_to_chat_bottom.borrow_mut();
-                *go_to_chat_bottom = true;
-            }
+            stream.send(SendMessage(
+                room_id as ChatServerID,
+                log_id as ChatClientID,
+                msg_id,
+                content.clone(),
+            ))?;
+            state.chat_data.chat_rooms[room_pos].chats[log_pos]
+                .messages
+                .push(ChatMessage {
+                    id: msg_id,
+                    content: Some(content),
+                    timestamp: now(),
+                    status: Some(SentToServer),
+                    reaction: None,
+                    edited: false,
+                    deleted: false,
+                    history: vec![],
+                });
+            let mut go_to_chat_bottom = state.ui_data.go_to_chat_bottom.borrow_mut();
+            *go_to_chat_bottom = true;
+            return Ok(true);
         }
     }
-    Ok(())
+    Ok(false)
 }
 
 fn select_last_message(state: &mut RefMut<TUIState>) -> Result<(), ClientError> {
@@ -500,8 +818,130 @@ fn select_last_message(state: &mut RefMut<TUIState>) -> Result<(), ClientError>
     Ok(())
 }
 
+fn selected_message(state: &RefMut<TUIState>) -> Option<ChatMessage> {
+    let room = &state.chat_data.chat_rooms[state.ui_data.current_room?];
+    let log = &room.chats[state.ui_data.current_log?];
+    let m_id = (*state.ui_data.selected_message.borrow())?;
+    log.messages.get(m_id).cloned()
+}
+
+fn open_save_prompt(state: &mut RefMut<TUIState>) {
+    if let Some(msg) = selected_message(state) {
+        if let Some(content) = &msg.content {
+            state.ui_data.path_in_editing = default_path(content, msg.id);
+            state.ui_data.active_component = FilePrompt(FileAction::SaveMessage);
+        }
+    }
+}
+
+//loads one of our messages in the editor of its kind
+fn start_editing_selected_message(state: &mut RefMut<TUIState>) {
+    let m_id = *state.ui_data.selected_message.borrow();
+    if let (Some(m_id), Some(msg)) = (m_id, selected_message(state)) {
+        if msg.status.is_none() {
+            return;
+        }
+        match msg.content {
+            Some(TextMessage(text)) => {
+                state.ui_data.text_message_in_edit = text;
+                state.ui_data.editing = Some(m_id);
+                state.ui_data.active_component = TextEdit;
+            }
+            Some(Drawing(drawing)) if state.ui_data.drawings_enabled => {
+                state.ui_data.drawing_in_edit.load(drawing);
+                state.ui_data.editing = Some(m_id);
+                state.ui_data.active_component = DrawingEdit;
+            }
+            _ => {}
+        }
+    }
+}
+
+//drops the edit in progress together with the content loaded in the editor
+fn stop_editing(state: &mut RefMut<TUIState>) {
+    if state.ui_data.editing.take().is_some() {
+        if let DrawingEdit = state.ui_data.active_component {
+            state.ui_data.drawing_in_edit.take();
+        } else {
+            state.ui_data.text_message_in_edit = "".to_string();
+        }
+    }
+}
+
+//opens on the current content, the older versions are to its left
+fn open_edit_history(state: &mut RefMut<TUIState>) {
+    if let Some(msg) = selected_message(state) {
+        if msg.content.is_some() && !msg.history.is_empty() {
+            state.ui_data.history_version = msg.history.len();
+            state.ui_data.active_component = EditHistory;
+        }
+    }
+}
+
+fn open_reaction_picker(state: &mut RefMut<TUIState>) {
+    if !state.ui_data.reactions_enabled {
+        return;
+    }
+    let m_id = *state.ui_data.selected_message.borrow();
+    if let (Some(m_id), Some(msg)) = (m_id, selected_message(state)) {
+        if msg.content.is_some() {
+            //the current reaction is picked again unless changed
+            let current = msg
+                .reaction
+                .and_then(|r| REACTIONS.iter().find(|option| **option == r));
+            state.ui_data.reacting_to = Some(m_id);
+            state.ui_data.selected_reaction = current.or(REACTIONS.first());
+            state.ui_data.active_component = ReactionSend;
+        }
+    }
+}
+
+fn close_reaction_picker(state: &mut RefMut<TUIState>) {
+    state.ui_data.reacting_to = None;
+    state.ui_data.selected_reaction = None;
+    state.ui_data.active_component = ChatView;
+}
+
+//moves through the reactions and the removal after them, wrapping around
+fn reaction_select_move(state: &mut RefMut<TUIState>, forward: bool) {
+    let options = REACTIONS.len() + 1;
+    let current = state
+        .ui_data
+        .selected_reaction
+        .and_then(|r| REACTIONS.iter().position(|option| option == r))
+        .unwrap_or(REACTIONS.len());
+    let next = if forward {
+        (current + 1) % options
+    } else {
+        (current + options - 1) % options
+    };
+    state.ui_data.selected_reaction = REACTIONS.get(next);
+}
+
+fn send_selected_reaction(
+    stream: &mut Connection,
+    state: &mut RefMut<TUIState>,
+) -> Result<(), ClientError> {
+    if let (Some(r_id), Some(l_id), Some(m_id)) = (
+        state.ui_data.current_room,
+        state.ui_data.current_log,
+        state.ui_data.reacting_to,
+    ) {
+        let reaction = state.ui_data.selected_reaction.copied();
+        let room = &mut state.chat_data.chat_rooms[r_id];
+        let log = &mut room.chats[l_id];
+        if let Some(msg) = log.messages.get_mut(m_id) {
+            if msg.reaction != reaction {
+                stream.send(ReactToMessage(room.id, log.id, msg.id, reaction))?;
+                msg.reaction = reaction;
+            }
+        }
+    }
+    Ok(())
+}
+
 fn delete_selected_message(
-    stream: &mut TcpStream,
+    stream: &mut Connection,
     state: &mut RefMut<TUIState>,
 ) -> Result<(), ClientError> {
     if let Some(r_id) = state.ui_data.current_room {
@@ -513,8 +953,9 @@ fn delete_selected_message(
                 let log = &mut room.chats[l_id];
                 let msg = &mut log.messages[m_id];
                 if msg.status.is_some() {
-                    send_message(stream, DeleteMessage(room.id, log.id, msg.id))?;
+                    stream.send(DeleteMessage(room.id, log.id, msg.id))?;
                     msg.content = None;
+                    msg.history.clear();
                 }
             }
         }
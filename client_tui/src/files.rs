@@ -0,0 +1,51 @@
+use client_lib::communication::{Drawing, MessageContent};
+use client_lib::pnm::{raster_size, to_pgm, to_ppm};
+use client_lib::svg::{from_svg, to_svg};
+use client_lib::ClientError;
+use client_lib::ClientError::{InvalidDrawing, Io};
+use std::fs;
+use std::path::Path;
+
+//width of the images drawings are saved as, the height follows the drawing
+const RASTER_WIDTH: u32 = 640;
+
+//text is saved as it is, drawings in the format named by the extension
+pub(crate) fn save_message(path: &Path, content: &MessageContent) -> Result<(), ClientError> {
+    let bytes = match content {
+        MessageContent::TextMessage(text) => text.clone().into_bytes(),
+        MessageContent::Drawing(drawing) => {
+            let (width, height) = raster_size(drawing, RASTER_WIDTH);
+            match extension(path).as_deref() {
+                Some("svg") => to_svg(drawing).into_bytes(),
+                Some("ppm") => to_ppm(drawing, width, height),
+                Some("pgm") => to_pgm(drawing, width, height),
+                _ => {
+                    return Err(InvalidDrawing(
+                        "drawings are saved as .svg, .ppm or .pgm".to_string(),
+                    ))
+                }
+            }
+        }
+    };
+    fs::write(path, bytes).map_err(Io)
+}
+
+pub(crate) fn load_drawing(path: &Path) -> Result<Drawing, ClientError> {
+    if extension(path).as_deref() != Some("svg") {
+        return Err(InvalidDrawing("only .svg files can be loaded".to_string()));
+    }
+    from_svg(&fs::read_to_string(path).map_err(Io)?)
+}
+
+//where a message is saved unless the user says otherwise
+pub(crate) fn default_path(content: &MessageContent, id: u64) -> String {
+    match content {
+        MessageContent::TextMessage(_) => format!("message-{}.txt", id),
+        MessageContent::Drawing(_) => format!("drawing-{}.svg", id),
+    }
+}
+
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+}
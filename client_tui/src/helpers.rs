@@ -1,20 +1,12 @@
+use client_lib::transport::{Endpoint, Transport};
 use client_lib::ClientError;
 use client_lib::ClientError::ListenerError;
-use std::net::TcpStream;
 
-pub(crate) fn get_stream(port: String) -> Result<TcpStream, ClientError> {
-
-    let stream = match TcpStream::connect(format!("127.0.0.1:{}", port)) {
-        Ok(listener) => listener,
-        Err(_) => {
-            return Err(ListenerError);
-        }
-    };
-
-    //debug
-    if stream.local_addr().is_err() {
-        return Err(ListenerError);
-    };
-
-    Ok(stream)
+//connects to the backend, the endpoint is either a bare tcp port or "tcp:"/"unix:" prefixed
+pub(crate) fn get_stream(endpoint: String) -> Result<Box<dyn Transport>, ClientError> {
+    let endpoint = endpoint.parse::<Endpoint>()?;
+    match endpoint.connect() {
+        Ok(stream) => Ok(stream),
+        Err(_) => Err(ListenerError),
+    }
 }
@@ -1,16 +1,26 @@
+pub mod cli;
 mod communication;
+pub mod config;
+mod drawing_editor;
 mod event_handling;
+mod files;
 mod helpers;
+pub mod logging;
 mod state;
 mod ui;
 
 use crate::communication::backend_command_receiver;
+use crate::config::TuiConfig;
 use crate::event_handling::handle_event;
 use crate::helpers::get_stream;
 use crate::state::TUIState;
 use crate::ui::ui;
+use client_lib::auth::{Auth, TOKEN_ENV};
+use client_lib::communication::TUIEvent::Dead;
+use client_lib::communication::{handshake, Capability, Connection};
+use client_lib::transport::Transport;
 use client_lib::ClientError;
-use client_lib::ClientError::{CrossTermError, LockError, StreamError, UIError};
+use client_lib::ClientError::{CrossTermError, LockError, UIError};
 use ratatui::crossterm::event::{DisableMouseCapture, EnableMouseCapture};
 use ratatui::crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
@@ -19,43 +29,115 @@ use ratatui::crossterm::{event, execute};
 use ratatui::prelude::CrosstermBackend;
 use ratatui::Terminal;
 use std::cell::RefCell;
-use std::io::stdout;
+use std::env;
+use std::io::{stdout, Stdout};
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
 use std::{thread, time};
 
-#[allow(unreachable_code)]
-pub fn loop_forever_chat_tui(port: String) -> Result<(), ClientError> {
+//features of the TUI protocol this frontend supports
+const BACKEND_CAPABILITIES: &[Capability] = &[
+    Capability::Drawings,
+    Capability::Reactions,
+    Capability::BinaryCodec,
+];
+
+pub fn loop_forever_chat_tui(endpoint: String) -> Result<(), ClientError> {
+    //GET CONNECTION TO CLIENT BACKEND
+    //a backend that spawned this TUI left its token in the environment
+    let token = env::var(TOKEN_ENV).ok();
+    loop_forever_chat_tui_over(
+        get_stream(endpoint)?,
+        token.as_deref(),
+        &TuiConfig::default(),
+    )
+}
+
+//runs the TUI over an already open stream, e.g. an in-memory one to a backend in this process,
+//until the backend kills it or the user quits
+pub fn loop_forever_chat_tui_over(
+    stream: Box<dyn Transport>,
+    token: Option<&str>,
+    config: &TuiConfig,
+) -> Result<(), ClientError> {
     //INITIALIZE STATE
-    let state = Arc::new(Mutex::new(RefCell::new(TUIState::new())));
+    let state = Arc::new(Mutex::new(RefCell::new(TUIState::new(config.theme))));
 
-    //GET TCP CONNECTION TO CLIENT BACKEND
-    let stream = get_stream(port)?;
-    let mut client_backend_stream = stream.try_clone().map_err(|_| StreamError)?;
+    let auth = match token {
+        Some(token) => Auth::Present(token),
+        None => Auth::Open,
+    };
+    let mut client_backend_stream = handshake(stream, BACKEND_CAPABILITIES, auth)?;
+    log::info!(
+        "connected to the backend, codec {:?}",
+        client_backend_stream.codec()
+    );
+    {
+        let state = state.lock().map_err(|_| LockError)?;
+        let mut state = state.borrow_mut();
+        let capabilities = client_backend_stream.capabilities();
+        state.ui_data.drawings_enabled = capabilities.contains(&Capability::Drawings);
+        state.ui_data.reactions_enabled = capabilities.contains(&Capability::Reactions);
+    }
 
     //BACKEND STATE RECEIVER THREAD
     let state_clone = Arc::clone(&state);
-    let stream_clone = client_backend_stream.try_clone().map_err(|_| StreamError)?;
-    thread::spawn(move || {
+    let stream_clone = client_backend_stream.try_clone()?;
+    let receiver = thread::spawn(move || {
         backend_command_receiver(state_clone, stream_clone);
     });
 
     //TUI PRE RUN STEPS
     enable_raw_mode().map_err(|_| UIError)?;
-    execute!(stdout(), EnterAlternateScreen, EnableMouseCapture).map_err(|_| UIError)?;
+    //the terminal is given back if it can't be set up
+    if execute!(stdout(), EnterAlternateScreen, EnableMouseCapture).is_err() {
+        let _ = disable_raw_mode();
+        return Err(UIError);
+    }
     let backend = CrosstermBackend::new(stdout());
-    let mut terminal = Terminal::new(backend).map_err(|_| UIError)?;
+    let Ok(mut terminal) = Terminal::new(backend) else {
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout(), LeaveAlternateScreen, DisableMouseCapture);
+        return Err(UIError);
+    };
+
+    let result = run_until_killed(&mut terminal, &state, &mut client_backend_stream);
+
+    //TUI POST RUN STEPS
+    //the backend keeps running for the next frontend, or is going away already
+    let _ = client_backend_stream.send(Dead);
+    client_backend_stream.shutdown();
+    let _ = receiver.join();
+    //the terminal is given back even if the loop failed
+    disable_raw_mode().map_err(|_| UIError)?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )
+    .map_err(|_| UIError)?;
+    terminal.show_cursor().map_err(|_| UIError)?;
+    result
+}
 
+fn run_until_killed(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    state: &Arc<Mutex<RefCell<TUIState>>>,
+    client_backend_stream: &mut Connection,
+) -> Result<(), ClientError> {
     loop {
         let thirty = time::Duration::from_millis(30);
         sleep(thirty);
         let state = state.lock().map_err(|_| LockError)?;
+        if state.borrow().kill {
+            return Ok(());
+        }
         let _ = terminal.draw(|frame| ui(frame, state.borrow()));
 
-        let event_available = event::poll(thirty).unwrap();
+        let event_available = event::poll(thirty).map_err(|_| UIError)?;
         if event_available {
             let event = event::read().map_err(|_| UIError)?;
-            let _ = handle_event(&mut client_backend_stream, state.borrow_mut(), event);
+            let _ = handle_event(client_backend_stream, state.borrow_mut(), event);
         }
 
         let state_borrow = state.borrow();
@@ -66,19 +148,4 @@ pub fn loop_forever_chat_tui(port: String) -> Result<(), ClientError> {
             execute!(terminal.backend_mut(), SetTitle(new_title)).map_err(|_| CrossTermError)?;
         }
     }
-
-    //TUI POST RUN STEPS
-    disable_raw_mode().map_err(|_| UIError)?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )
-    .map_err(|_| UIError)?;
-    terminal.show_cursor().map_err(|_| UIError)?;
-
-    //TESTING MESSAGE TO CLIENT
-    //send_message(&mut stream, SetName("pippo".to_string()))?;
-
-    Ok(())
 }
@@ -0,0 +1,58 @@
+use client_lib::ClientError;
+use client_lib::ClientError::{EnvError, Io};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+//the terminal belongs to the TUI, so logs can only go to a file
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let seconds = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                seconds,
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+//sends the log messages of the whole process to the file, appending to it
+pub fn log_to_file(path: &Path) -> Result<(), ClientError> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(Io)?;
+    let logger = Box::leak(Box::new(FileLogger {
+        file: Mutex::new(file),
+    }));
+    //only one logger per process
+    log::set_logger(logger).map_err(|_| EnvError)?;
+    log::set_max_level(LevelFilter::Debug);
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use crate::config::Theme;
+use crate::drawing_editor::DrawingEditor;
 use crate::state::ActiveComponent::RoomSelect;
 use client_lib::communication::{
     ChatClientID, ChatServerID, MessageContent, MessageID, MessageStatus, Reaction, TimeStamp,
@@ -55,6 +57,7 @@ pub struct ChatLog {
     pub id: ChatClientID,
     pub messages: Vec<ChatMessage>,
     pub peer_name: String,
+    //0 until the peer sends something
     pub last_seen: TimeStamp,
     pub currently_creating: MessageContent,
     pub pending: u32,
@@ -65,11 +68,14 @@ pub struct ChatLog {
 pub struct ChatMessage {
     pub id: MessageID,
     pub content: Option<MessageContent>,
+    //when it was sent, by the sender's clock
     pub timestamp: TimeStamp,
     pub status: Option<MessageStatus>,
     pub reaction: Option<Reaction>,
     pub edited: bool,
     pub deleted: bool,
+    //what the message said before each edit, oldest first; never sent anywhere
+    pub history: Vec<MessageContent>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,20 +86,31 @@ pub(crate) enum ActiveComponent {
     ChatSelect,
     ChatView,
     TextEdit,
+    DrawingEdit,
     ReactionSend,
+    EditHistory,
     NameSet(NameSetAction),
+    FilePrompt(FileAction),
 }
 #[derive(Debug, Clone)]
 pub(crate) enum NameSetAction {
     Displaying,
     ChangingName,
 }
+#[derive(Debug, Clone)]
+pub(crate) enum FileAction {
+    SaveMessage,
+    LoadDrawing,
+}
 
 #[derive(Debug)]
 pub(crate) struct TUIState<'a> {
     pub chat_data: ChatData,
     pub ui_data: UIData<'a>,
+    //the backend sent Kill or the user quit, the TUI exits
     pub kill: bool,
+    //why the backend stopped answering, shown instead of the chat
+    pub backend_gone: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +128,18 @@ pub(crate) struct UIData<'a> {
     pub current_log: Option<usize>,
     pub selected_log: Option<usize>,
     pub text_message_in_edit: String,
+    pub drawing_in_edit: DrawingEditor,
+    //message whose content is in the editor, sending replaces it instead of adding one
+    pub editing: Option<usize>,
+    //version of the selected message shown in the edit history
+    pub history_version: usize,
+    //whether the backend accepts drawings, the composer is off otherwise
+    pub drawings_enabled: bool,
+    //whether the backend passes reactions on, the picker is off otherwise
+    pub reactions_enabled: bool,
+    pub path_in_editing: String,
+    //outcome of the last file action, shown until the next key
+    pub notice: Option<String>,
     pub reacting_to: Option<usize>,
     pub selected_reaction: Option<&'a Reaction>,
     pub name_in_editing: Option<String>,
@@ -119,10 +148,11 @@ pub(crate) struct UIData<'a> {
     pub selected_message: RefCell<Option<usize>>,
     pub change_window_title: bool,
     pub new_window_title: String,
+    pub theme: Theme,
 }
 
 impl TUIState<'_> {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(theme: Theme) -> Self {
         TUIState {
             chat_data: ChatData {
                 chat_rooms: vec![],
@@ -135,6 +165,13 @@ impl TUIState<'_> {
                 current_log: None,
                 selected_log: None,
                 text_message_in_edit: "".to_string(),
+                drawing_in_edit: DrawingEditor::new(),
+                editing: None,
+                history_version: 0,
+                drawings_enabled: false,
+                reactions_enabled: false,
+                path_in_editing: "".to_string(),
+                notice: None,
                 reacting_to: None,
                 selected_reaction: None,
                 name_in_editing: None,
@@ -143,8 +180,10 @@ impl TUIState<'_> {
                 selected_message: RefCell::new(None),
                 change_window_title: false,
                 new_window_title: "".to_string(),
+                theme,
             },
             kill: false,
+            backend_gone: None,
         }
     }
 }
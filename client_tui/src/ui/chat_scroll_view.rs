@@ -1,22 +1,45 @@
+use crate::config::Theme;
 use crate::state::ChatMessage;
+use crate::ui::ui_utils::{
+    local_time, paint_shapes, reaction_symbol, status_symbol, NO_REACTION_SYMBOL, REACTIONS,
+};
+use chrono::Local;
 use client_lib::communication::MessageContent::*;
+use client_lib::communication::{Drawing, MessageStatus, Reaction};
 use ratatui::buffer::Buffer;
 use ratatui::layout::Constraint::{Fill, Length};
 use ratatui::layout::{Layout, Position, Rect, Size};
 use ratatui::prelude::StatefulWidget;
-use ratatui::style::{Style, Stylize};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line as TextLine, Span};
+use ratatui::widgets::canvas::{Canvas, Context};
 use ratatui::widgets::BorderType::Rounded;
-use ratatui::widgets::{Block, Paragraph, Widget, Wrap};
+use ratatui::widgets::{Block, Clear, Paragraph, Widget, Wrap};
 use std::cell::RefMut;
 use tui_scrollview::ScrollbarVisibility::{Always, Never};
 use tui_scrollview::{ScrollView, ScrollViewState};
 use unicode_width::UnicodeWidthStr;
 
+//rows a drawing bubble takes inside its borders, however wide or tall the drawing is
+const MIN_DRAWING_ROWS: u16 = 4;
+const MAX_DRAWING_ROWS: u16 = 20;
+//a reaction badge is a symbol with a space on each side
+const REACTION_BADGE_WIDTH: u16 = 4;
+const EDITED_MARKER: &str = "(edited)";
+//"hh:mm" on the top border
+const TIME_WIDTH: u16 = 5;
+//six options of four cells and the borders
+const REACTION_PICKER_WIDTH: u16 = 26;
+
 pub(crate) struct ChatScrollView<'a> {
     pub(crate) messages: &'a Vec<ChatMessage>,
     pub(crate) scroll_view_state: RefMut<'a, ScrollViewState>,
     pub(crate) go_to_chat_bottom: RefMut<'a, bool>,
     pub(crate) selected_message: RefMut<'a, Option<usize>>,
+    pub(crate) theme: &'a Theme,
+    //(message reacted to, reaction picked) while the picker is open
+    pub(crate) reaction_picker: Option<(usize, Option<Reaction>)>,
 }
 
 impl<'a> ChatScrollView<'a> {
@@ -25,23 +48,94 @@ impl<'a> ChatScrollView<'a> {
         let mut current_height = 0;
         let line_w = area.width - 2;
         for (i, m) in self.messages.iter().enumerate() {
-            let p = self.message(m, i);
+            if let Some(day) = self.day_started_by(i) {
+                let rect = Rect::new(area.x, current_height, line_w, 1);
+                TextLine::styled(format!("── {} ──", day), Style::new().fg(Color::Gray))
+                    .centered()
+                    .render(rect, buf);
+                current_height += 1;
+            }
             let msg_w = Self::get_msg_width(m, line_w);
-            let h = p.line_count(msg_w - 2) as u16;
+            let h = self.get_msg_height(m, i, msg_w);
             let rect = Rect::new(area.x, current_height, line_w, h);
 
-            if m.status.is_some() {
-                let msg_rect = Layout::horizontal([Fill(1), Length(msg_w)]).areas::<2>(rect)[1];
-                p.render(msg_rect, buf);
+            let msg_rect = if m.status.is_some() {
+                Layout::horizontal([Fill(1), Length(msg_w)]).areas::<2>(rect)[1]
+            } else {
+                Layout::horizontal([Length(msg_w), Fill(1)]).areas::<2>(rect)[0]
+            };
+            if let Some(Drawing(d)) = &m.content {
+                self.drawing(d, m, i, msg_rect).render(msg_rect, buf);
             } else {
-                let msg_rect = Layout::horizontal([Length(msg_w), Fill(1)]).areas::<2>(rect)[0];
-                p.render(msg_rect, buf);
+                self.message(m, i).render(msg_rect, buf);
+            }
+            if let Some((reacting_to, selected)) = self.reaction_picker {
+                if reacting_to == i {
+                    self.draw_reaction_picker(buf, msg_rect, m.status.is_some(), selected);
+                }
             }
 
             current_height += h;
         }
     }
 
+    //drawn over the top of the message, on the same side of the chat
+    fn draw_reaction_picker(
+        &self,
+        buf: &mut Buffer,
+        msg_rect: Rect,
+        own: bool,
+        selected: Option<Reaction>,
+    ) {
+        let width = REACTION_PICKER_WIDTH.min(buf.area.width);
+        let x = if own {
+            (msg_rect.x + msg_rect.width).saturating_sub(width)
+        } else {
+            msg_rect.x
+        };
+        let rect = Rect::new(x, msg_rect.y, width, 3).intersection(buf.area);
+        let highlighted = Style::new().fg(Color::Black).bg(self.theme.accent);
+        let options = REACTIONS
+            .iter()
+            .map(|r| (Some(*r), reaction_symbol(r)))
+            .chain([(None, NO_REACTION_SYMBOL)])
+            .map(|(option, symbol)| {
+                let style = if option == selected {
+                    highlighted
+                } else {
+                    Style::new()
+                };
+                Span::styled(format!(" {} ", symbol), style)
+            })
+            .collect::<Vec<_>>();
+        Clear.render(rect, buf);
+        Paragraph::new(TextLine::from(options))
+            .block(
+                Block::bordered()
+                    .border_type(Rounded)
+                    .border_style(Style::new().fg(self.theme.accent))
+                    .title("React"),
+            )
+            .render(rect, buf);
+    }
+
+    //the label of the separator above the message, if it is the first of its day
+    fn day_started_by(&self, id: usize) -> Option<String> {
+        let day = |m: &ChatMessage| local_time(m.timestamp).map(|t| t.date_naive());
+        let today = Local::now().date_naive();
+        let date = day(&self.messages[id])?;
+        if id > 0 && day(&self.messages[id - 1]) == Some(date) {
+            return None;
+        }
+        Some(if date == today {
+            "Today".to_string()
+        } else if today.pred_opt() == Some(date) {
+            "Yesterday".to_string()
+        } else {
+            date.format("%a %-d %b %Y").to_string()
+        })
+    }
+
     fn get_msg_width(m: &ChatMessage, w: u16) -> u16 {
         let mut msg_w = (w * 80) / 100;
         if let Some(c) = &m.content {
@@ -50,6 +144,27 @@ impl<'a> ChatScrollView<'a> {
                 if len + 2 < msg_w as usize {
                     msg_w = (len + 2) as u16;
                 }
+                //room for the marks on the bottom border and its corners
+                let mut marks = 0;
+                if m.reaction.is_some() {
+                    marks += REACTION_BADGE_WIDTH;
+                }
+                if m.edited {
+                    marks += EDITED_MARKER.len() as u16;
+                }
+                if marks > 0 {
+                    msg_w = msg_w.max(marks + 2);
+                }
+                let mut top = 0;
+                if local_time(m.timestamp).is_some() {
+                    top += TIME_WIDTH;
+                }
+                if let Some(status) = &m.status {
+                    top += status_symbol(status).width() as u16 + 1;
+                }
+                if top > 0 {
+                    msg_w = msg_w.max(top + 2);
+                }
             }
         } else {
             msg_w = 21;
@@ -61,51 +176,142 @@ impl<'a> ChatScrollView<'a> {
         let mut current_height = 0;
         let line_w = w - 2;
         for (i, m) in self.messages.iter().enumerate() {
-            let p = self.message(m, i);
+            if self.day_started_by(i).is_some() {
+                current_height += 1;
+            }
             let msg_w = Self::get_msg_width(m, line_w);
-            let h = p.line_count(msg_w - 2) as u16;
-            current_height += h;
+            current_height += self.get_msg_height(m, i, msg_w);
         }
         current_height
     }
 
-    fn message(&self, m: &ChatMessage, id: usize) -> Paragraph {
+    fn get_msg_height(&self, m: &ChatMessage, id: usize, msg_w: u16) -> u16 {
+        if let Some(Drawing(d)) = &m.content {
+            return Self::get_drawing_rows(d, msg_w - 2) + 2;
+        }
+        self.message(m, id).line_count(msg_w - 2) as u16
+    }
+
+    //rows needed to show the drawing at full width without stretching it,
+    //a cell is about twice as tall as it is wide
+    fn get_drawing_rows(d: &Drawing, cols: u16) -> u16 {
+        let rows = match d.bounds() {
+            Some((min_x, min_y, max_x, max_y)) => {
+                let w = (max_x - min_x).max(1.0);
+                let h = (max_y - min_y).max(1.0);
+                (cols as f64 * h / w / 2.0).ceil() as u16
+            }
+            None => 0,
+        };
+        rows.clamp(MIN_DRAWING_ROWS, MAX_DRAWING_ROWS)
+    }
+
+    fn drawing(
+        &self,
+        d: &'a Drawing,
+        m: &ChatMessage,
+        id: usize,
+        rect: Rect,
+    ) -> Canvas<'a, impl Fn(&mut Context) + 'a> {
+        let inner = Block::bordered().inner(rect);
+        let (x_bounds, y_bounds) = canvas_bounds(d, inner.width, inner.height);
+        Canvas::default()
+            .block(self.message_block(m, id))
+            .marker(Marker::Braille)
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds)
+            .paint(move |ctx| paint_shapes(ctx, d.shapes()))
+    }
+
+    fn message_block(&self, m: &ChatMessage, id: usize) -> Block<'a> {
         let mut border_style = Style::default();
         if let Some(sm) = *self.selected_message {
             if sm == id {
-                border_style = Style::new().green();
+                border_style = Style::new().fg(self.theme.accent);
             }
         }
-        let mc = &m.content;
-        if let Some(mci) = mc {
-            return if let TextMessage(s) = mci {
-                Paragraph::new(s.clone())
-                    .block(
-                        Block::bordered()
-                            .border_type(Rounded)
-                            .border_style(border_style),
-                    )
-                    .wrap(Wrap { trim: false })
-            } else {
-                Paragraph::new("Disegno")
-                    .block(
-                        Block::bordered()
-                            .border_type(Rounded)
-                            .border_style(border_style),
-                    )
-                    .wrap(Wrap { trim: false })
-            };
+        let mut block = Block::bordered()
+            .border_type(Rounded)
+            .border_style(border_style);
+        let mut top = vec![];
+        if let Some(time) = local_time(m.timestamp) {
+            top.push(Span::styled(
+                time.format("%H:%M").to_string(),
+                Style::new().fg(Color::Gray),
+            ));
         }
-        Paragraph::new("Messaggio eliminato")
-            .block(
-                Block::bordered()
-                    .border_type(Rounded)
-                    .border_style(border_style),
-            )
+        if let Some(status) = &m.status {
+            top.push(Span::styled(
+                format!(" {}", status_symbol(status)),
+                self.status_style(status),
+            ));
+        }
+        if !top.is_empty() {
+            block = block.title(TextLine::from(top).right_aligned());
+        }
+        if m.content.is_none() {
+            return block;
+        }
+        if m.edited {
+            block = block.title_bottom(
+                TextLine::styled(EDITED_MARKER, Style::new().fg(Color::Gray)).left_aligned(),
+            );
+        }
+        if let Some(reaction) = &m.reaction {
+            block = block.title_bottom(
+                TextLine::from(format!(" {} ", reaction_symbol(reaction))).right_aligned(),
+            );
+        }
+        block
+    }
+
+    fn status_style(&self, status: &MessageStatus) -> Style {
+        match status {
+            MessageStatus::ReadByPeer => Style::new().fg(self.theme.accent),
+            MessageStatus::FailedToSend => Style::new().fg(self.theme.error),
+            _ => Style::new().fg(Color::Gray),
+        }
+    }
+
+    fn message(&self, m: &ChatMessage, id: usize) -> Paragraph {
+        let text = match &m.content {
+            Some(TextMessage(s)) => s.clone(),
+            //drawings are rendered on a canvas, see drawing()
+            Some(Drawing(_)) => String::new(),
+            None => "Messaggio eliminato".to_string(),
+        };
+        Paragraph::new(text)
+            .block(self.message_block(m, id))
             .wrap(Wrap { trim: false })
     }
 }
 
+//the (x, y) bounds of the canvas: the drawing with a small margin, widened on one axis
+//so that it keeps its proportions in cols x rows cells, y flipped to grow upwards
+pub(super) fn canvas_bounds(d: &Drawing, cols: u16, rows: u16) -> ([f64; 2], [f64; 2]) {
+    let (min_x, min_y, max_x, max_y) = d.bounds().unwrap_or((0.0, 0.0, 1.0, 1.0));
+    let margin = ((max_x - min_x).max(max_y - min_y) * 0.02).max(0.5);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (
+        min_x - margin,
+        min_y - margin,
+        max_x + margin,
+        max_y + margin,
+    );
+    //width over height of the area, in drawing units
+    let area_ratio = cols.max(1) as f64 / (rows.max(1) as f64 * 2.0);
+    let (w, h) = (max_x - min_x, max_y - min_y);
+    if w / h < area_ratio {
+        let extra = (h * area_ratio - w) / 2.0;
+        min_x -= extra;
+        max_x += extra;
+    } else {
+        let extra = (w / area_ratio - h) / 2.0;
+        min_y -= extra;
+        max_y += extra;
+    }
+    ([min_x, max_x], [-max_y, -min_y])
+}
+
 impl<'a> Widget for &mut ChatScrollView<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let h = self.get_height(area.width - 2);
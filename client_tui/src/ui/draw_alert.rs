@@ -1,17 +1,17 @@
+use crate::config::Theme;
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::prelude::Style;
-use ratatui::style::Stylize;
 use ratatui::widgets::BorderType::Rounded;
 use ratatui::widgets::{Block, Paragraph, Wrap};
 use ratatui::Frame;
 
-pub(super) fn draw_alert(frame: &mut Frame, rect: Rect, text: &str) {
+pub(super) fn draw_alert(frame: &mut Frame, rect: Rect, theme: &Theme, text: &str) {
     let p = Paragraph::new(text)
         .block(
             Block::bordered()
                 .border_type(Rounded)
                 .title("Alert")
-                .border_style(Style::new().red()),
+                .border_style(Style::new().fg(theme.error)),
         )
         .alignment(Alignment::Left)
         .wrap(Wrap { trim: false });
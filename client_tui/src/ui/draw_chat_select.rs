@@ -1,6 +1,8 @@
 use crate::state::ActiveComponent::*;
 use crate::state::TUIState;
 use crate::ui::draw_alert::draw_alert;
+use crate::ui::ui_utils::relative_time;
+use client_lib::communication::now;
 use ratatui::layout::{Alignment, Constraint, Rect};
 use ratatui::prelude::{Style, Text};
 use ratatui::style::Stylize;
@@ -10,8 +12,9 @@ use ratatui::Frame;
 use std::cell::Ref;
 
 pub(super) fn draw_chat_select(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let border_style = if let ChatSelect = state.ui_data.active_component {
-        Style::new().green()
+        Style::new().fg(theme.accent)
     } else {
         Style::new()
     };
@@ -29,10 +32,15 @@ pub(super) fn draw_chat_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
             None
         };
 
+        let now = now();
         let mut rows = Vec::new();
         for log in &room.chats {
             let peer_name = log.peer_name.clone();
-            let last_seen = log.last_seen.to_string();
+            let last_seen = if log.last_seen == 0 {
+                "never".to_string()
+            } else {
+                relative_time(log.last_seen, now)
+            };
             let online = if log.net_reachable { '🌐' } else { '❌' };
             let unread = if log.pending > 0 {
                 log.pending.to_string()
@@ -41,19 +49,19 @@ pub(super) fn draw_chat_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
             };
             let mut row_style = Style::new();
             let mut pending_style = if log.pending > 0 {
-                Style::new().yellow()
+                Style::new().fg(theme.pending)
             } else {
-                Style::new().green()
+                Style::new().fg(theme.ok)
             };
             let mut online_style = if log.net_reachable {
-                Style::new().on_green()
+                Style::new().bg(theme.ok)
             } else {
-                Style::new().on_red()
+                Style::new().bg(theme.error)
             };
 
             if let Some(l) = current_log {
                 if l.id == log.id {
-                    row_style = Style::new().black().on_green();
+                    row_style = Style::new().black().bg(theme.accent);
                     pending_style = row_style.clone();
                     online_style = Style::new();
                 }
@@ -106,12 +114,12 @@ pub(super) fn draw_chat_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
         };
 
         let header_style_1 = if let ChatSelect = &state.ui_data.active_component {
-            Style::new().black().on_green()
+            Style::new().black().bg(theme.accent)
         } else {
             Style::new().on_black()
         };
         let header_style_2 = if let ChatSelect = &state.ui_data.active_component {
-            Style::new().black().on_light_green()
+            Style::new().black().bg(theme.accent_light)
         } else {
             Style::new()
         };
@@ -143,6 +151,6 @@ pub(super) fn draw_chat_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
             .style(Style::new());
 
         frame.render_widget(block, rect);
-        draw_alert(frame, rect, "Select a room first!");
+        draw_alert(frame, rect, theme, "Select a room first!");
     }
 }
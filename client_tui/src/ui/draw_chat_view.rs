@@ -4,15 +4,15 @@ use crate::ui::chat_scroll_view::ChatScrollView;
 use crate::ui::draw_alert::draw_alert;
 use ratatui::layout::Rect;
 use ratatui::prelude::Style;
-use ratatui::style::Stylize;
 use ratatui::widgets::Block;
 use ratatui::widgets::BorderType::Rounded;
 use ratatui::Frame;
 use std::cell::Ref;
 
 pub(super) fn draw_chat_view(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let border_style = if let ChatView = state.ui_data.active_component {
-        Style::new().green()
+        Style::new().fg(theme.accent)
     } else {
         Style::new()
     };
@@ -29,14 +29,20 @@ pub(super) fn draw_chat_view(frame: &mut Frame, rect: Rect, state: &Ref<TUIState
                 scroll_view_state,
                 go_to_chat_bottom,
                 selected_message,
+                theme,
+                reaction_picker: state
+                    .ui_data
+                    .reacting_to
+                    .map(|m_id| (m_id, state.ui_data.selected_reaction.copied())),
             };
             let inner = Rect::new(rect.x + 1, rect.y + 1, rect.width - 1, rect.height - 2);
-            frame.render_widget(
-                Block::bordered()
-                    .border_style(border_style)
-                    .border_type(Rounded),
-                rect,
-            );
+            let mut block = Block::bordered()
+                .border_style(border_style)
+                .border_type(Rounded);
+            if let Some(notice) = &state.ui_data.notice {
+                block = block.title_bottom(notice.as_str());
+            }
+            frame.render_widget(block, rect);
             frame.render_widget(&mut chat_scroll_view, inner);
         }
     } else {
@@ -48,6 +54,6 @@ pub(super) fn draw_chat_view(frame: &mut Frame, rect: Rect, state: &Ref<TUIState
                 .style(Style::new()),
             rect,
         );
-        draw_alert(frame, rect, "Select a friend first!")
+        draw_alert(frame, rect, theme, "Select a friend first!")
     }
 }
@@ -0,0 +1,124 @@
+use crate::drawing_editor::PALETTE;
+use crate::state::ActiveComponent::DrawingEdit;
+use crate::state::TUIState;
+use crate::ui::ui_utils::{paint_shapes, rgb};
+use ratatui::layout::Rect;
+use ratatui::prelude::Style;
+use ratatui::style::Color;
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::canvas::{Canvas, Line as CanvasLine};
+use ratatui::widgets::BorderType::Rounded;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+use std::cell::Ref;
+
+//half the length of the cursor cross arms
+const CURSOR_SIZE: f64 = 1.5;
+
+//the composer takes the chat view while it is open, its status goes where the text editor was
+pub(super) fn draw_drawing_edit(frame: &mut Frame, canvas_rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
+    let editor = &state.ui_data.drawing_in_edit;
+    let border_style = if let DrawingEdit = state.ui_data.active_component {
+        Style::new().fg(theme.accent)
+    } else {
+        Style::new()
+    };
+
+    let title = if state.ui_data.editing.is_some() {
+        "Edit your drawing!"
+    } else {
+        "Draw something!"
+    };
+    let mut block = Block::bordered()
+        .border_type(Rounded)
+        .border_style(border_style)
+        .title(title);
+    if let Some(notice) = &state.ui_data.notice {
+        block = block.title_bottom(notice.as_str());
+    }
+    editor.canvas_area.set(block.inner(canvas_rect));
+    let preview = editor.preview();
+    let (x, y) = editor.cursor;
+    let (min_x, min_y, max_x, max_y) = editor.view();
+    let cursor_color = theme.accent;
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([min_x, max_x])
+        .y_bounds([-max_y, -min_y])
+        .paint(|ctx| {
+            paint_shapes(ctx, editor.drawing.shapes());
+            if let Some(shape) = &preview {
+                paint_shapes(ctx, std::slice::from_ref(shape));
+            }
+            ctx.layer();
+            ctx.draw(&CanvasLine::new(
+                x - CURSOR_SIZE,
+                -y,
+                x + CURSOR_SIZE,
+                -y,
+                cursor_color,
+            ));
+            ctx.draw(&CanvasLine::new(
+                x,
+                -y - CURSOR_SIZE,
+                x,
+                -y + CURSOR_SIZE,
+                cursor_color,
+            ));
+        });
+    frame.render_widget(canvas, canvas_rect);
+}
+
+pub(super) fn draw_drawing_status(frame: &mut Frame, status_rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
+    let editor = &state.ui_data.drawing_in_edit;
+    let (x, y) = editor.cursor;
+
+    let (color_name, color) = &PALETTE[editor.color];
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw(format!("Tool: {:<10} Colour: ", editor.tool.name())),
+            Span::styled("██ ", Style::new().fg(rgb(color))),
+            Span::raw(format!("{} ({})", color_name, editor.color + 1)),
+        ]),
+        Line::from(format!(
+            "Cursor: {:.0}, {:.0}   Shapes: {}",
+            x,
+            y,
+            editor.drawing.shapes().len()
+        )),
+    ];
+    if let Some((ax, ay)) = editor.anchor {
+        lines.push(Line::styled(
+            format!(
+                "Drawing a {} from {:.0}, {:.0}: place again to finish",
+                editor.tool.name(),
+                ax,
+                ay
+            ),
+            Style::new().fg(theme.pending),
+        ));
+    }
+    let palette = PALETTE
+        .iter()
+        .enumerate()
+        .flat_map(|(i, (_, c))| {
+            [
+                Span::styled(format!("{}", i + 1), Style::new().fg(Color::Gray)),
+                Span::styled("█ ", Style::new().fg(rgb(c))),
+            ]
+        })
+        .collect::<Vec<_>>();
+    lines.push(Line::from(palette));
+
+    let status = Paragraph::new(lines).block(
+        Block::bordered()
+            .border_type(Rounded)
+            .border_style(Style::new().fg(theme.accent))
+            .title("Drawing"),
+    );
+    frame.render_widget(status, status_rect);
+}
@@ -0,0 +1,63 @@
+use crate::state::TUIState;
+use crate::ui::chat_scroll_view::canvas_bounds;
+use crate::ui::ui_utils::paint_shapes;
+use client_lib::communication::MessageContent::{Drawing, TextMessage};
+use ratatui::layout::Rect;
+use ratatui::prelude::Style;
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::Canvas;
+use ratatui::widgets::BorderType::Rounded;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::Frame;
+use std::cell::Ref;
+
+//one version of the selected message at a time, where the text editor is
+pub(super) fn draw_edit_history(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
+    let msg = state
+        .ui_data
+        .current_room
+        .zip(state.ui_data.current_log)
+        .zip(*state.ui_data.selected_message.borrow())
+        .and_then(|((r, l), m)| state.chat_data.chat_rooms[r].chats[l].messages.get(m));
+    let Some(msg) = msg else {
+        return;
+    };
+    //the versions are the history and the current content
+    let versions = msg.history.iter().chain(&msg.content).collect::<Vec<_>>();
+    //deleted while looking at it
+    if versions.is_empty() {
+        return;
+    }
+    let shown = state.ui_data.history_version.min(versions.len() - 1);
+    let mut title = format!("Version {} of {}", shown + 1, versions.len());
+    if shown == 0 {
+        title.push_str(" (original)");
+    } else if shown == versions.len() - 1 {
+        title.push_str(" (current)");
+    }
+    let block = Block::bordered()
+        .border_type(Rounded)
+        .border_style(Style::new().fg(theme.accent))
+        .title(title);
+
+    match versions[shown] {
+        TextMessage(text) => {
+            let version = Paragraph::new(text.as_str())
+                .wrap(Wrap { trim: false })
+                .block(block);
+            frame.render_widget(version, rect);
+        }
+        Drawing(d) => {
+            let inner = block.inner(rect);
+            let (x_bounds, y_bounds) = canvas_bounds(d, inner.width, inner.height);
+            let version = Canvas::default()
+                .block(block)
+                .marker(Marker::Braille)
+                .x_bounds(x_bounds)
+                .y_bounds(y_bounds)
+                .paint(|ctx| paint_shapes(ctx, d.shapes()));
+            frame.render_widget(version, rect);
+        }
+    }
+}
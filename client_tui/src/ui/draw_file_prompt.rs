@@ -0,0 +1,32 @@
+use crate::state::{FileAction, TUIState};
+use ratatui::layout::Rect;
+use ratatui::prelude::Style;
+use ratatui::widgets::BorderType::Rounded;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::Frame;
+use std::cell::Ref;
+
+//asks for a path where the text editor is
+pub(super) fn draw_file_prompt(
+    frame: &mut Frame,
+    rect: Rect,
+    state: &Ref<TUIState>,
+    action: &FileAction,
+) {
+    let theme = &state.ui_data.theme;
+    let title = match action {
+        FileAction::SaveMessage => "Save message to",
+        FileAction::LoadDrawing => "Load drawing from (.svg)",
+    };
+    let mut block = Block::bordered()
+        .border_type(Rounded)
+        .border_style(Style::new().fg(theme.accent))
+        .title(title);
+    if let Some(notice) = &state.ui_data.notice {
+        block = block.title_bottom(notice.as_str());
+    }
+    let prompt = Paragraph::new(format!("{}_", state.ui_data.path_in_editing))
+        .wrap(Wrap { trim: false })
+        .block(block);
+    frame.render_widget(prompt, rect);
+}
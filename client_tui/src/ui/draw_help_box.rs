@@ -1,13 +1,17 @@
 use crate::state::ActiveComponent::*;
 use crate::state::{NameSetAction, TUIState};
+use crate::ui::ui_utils::{reaction_symbol, REACTIONS};
+use client_lib::communication::MessageContent::{Drawing, TextMessage};
 use ratatui::layout::{Alignment, Rect};
-use ratatui::style::{Style, Stylize};
+use ratatui::style::Style;
+use ratatui::text::Line;
 use ratatui::widgets::BorderType::Rounded;
 use ratatui::widgets::{Block, Paragraph};
 use ratatui::Frame;
 use std::cell::Ref;
 
 pub(super) fn draw_help_box(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let mut text = "".to_string();
     match &state.ui_data.active_component {
         NameSet(a) => {
@@ -68,6 +72,24 @@ pub(super) fn draw_help_box(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>
                             let msg = &log.messages[m_id];
                             if msg.status.is_some() {
                                 text.push_str("<D>          : Delete message\n");
+                                match msg.content {
+                                    Some(TextMessage(_)) => {
+                                        text.push_str("<E>          : Edit message\n");
+                                    }
+                                    Some(Drawing(_)) if state.ui_data.drawings_enabled => {
+                                        text.push_str("<E>          : Edit message\n");
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            if msg.content.is_some() && !msg.history.is_empty() {
+                                text.push_str("<H>          : Edit history\n");
+                            }
+                            if msg.content.is_some() {
+                                text.push_str("<S>          : Save message\n");
+                                if state.ui_data.reactions_enabled {
+                                    text.push_str("<R>          : React to message\n");
+                                }
                             }
                         }
                     }
@@ -75,19 +97,72 @@ pub(super) fn draw_help_box(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>
             }
         }
         TextEdit => {
-            text.push_str("Write your message!\n");
-            text.push_str("<C-Up>       : Go to chat view\n");
-            text.push_str("<C-Left>     : Go to chats\n");
-            text.push_str("<Enter>      : New Line\n");
-            text.push_str("<C-S>        : Send message\n");
+            if state.ui_data.editing.is_some() {
+                text.push_str("Edit your message!\n");
+                text.push_str("<Enter>      : New Line\n");
+                text.push_str("<C-S>        : Send edit\n");
+                text.push_str("<Esc>        : Cancel edit\n");
+            } else {
+                text.push_str("Write your message!\n");
+                text.push_str("<C-Up>       : Go to chat view\n");
+                text.push_str("<C-Left>     : Go to chats\n");
+                text.push_str("<Enter>      : New Line\n");
+                text.push_str("<C-S>        : Send message\n");
+                if state.ui_data.drawings_enabled {
+                    text.push_str("<C-D>        : Draw something\n");
+                }
+            }
+        }
+        DrawingEdit => {
+            text.push_str("<Arrows>     : Move cursor\n");
+            text.push_str("<Space|Click>: Place shape\n");
+            text.push_str("<P|L|R|C>    : Pick shape\n");
+            text.push_str("<Tab|1-8>    : Change colour\n");
+            text.push_str("<U|X|O>      : Undo|Clear|Open\n");
+            if state.ui_data.editing.is_some() {
+                text.push_str("<C-S>        : Send edit\n");
+                text.push_str("<Esc>        : Cancel edit\n");
+            } else {
+                text.push_str("<C-S>        : Send drawing\n");
+                text.push_str("<Esc|C-D>    : Back to text\n");
+            }
+        }
+        ReactionSend => {
+            text.push_str("Pick a reaction!\n");
+            text.push_str("<Left|Right> : Choose reaction\n");
+            match state.ui_data.selected_reaction {
+                Some(r) => text.push_str(&format!("<Enter>      : React with {:?}\n", r)),
+                None => text.push_str("<Enter>      : Remove reaction\n"),
+            }
+            text.push_str("<Esc>        : Cancel action\n");
+            for row in REACTIONS.chunks(3) {
+                let badges = row
+                    .iter()
+                    .map(|r| format!("{} {:?}", reaction_symbol(r), r))
+                    .collect::<Vec<_>>();
+                text.push_str(&badges.join("  "));
+                text.push('\n');
+            }
+        }
+        EditHistory => {
+            text.push_str("Earlier versions of the message\n");
+            text.push_str("<Left|Right> : Browse versions\n");
+            text.push_str("<Esc|H>      : Back to chat\n");
+        }
+        FilePrompt(_) => {
+            text.push_str("Write the file path!\n");
+            text.push_str("<Esc>        : Cancel action\n");
+            text.push_str("<Enter>      : Confirm\n");
         }
         _ => {}
     }
 
     let b = Block::bordered()
         .border_type(Rounded)
-        .border_style(Style::new().magenta())
-        .title("Help");
+        .border_style(Style::new().fg(theme.help))
+        .title("Help")
+        //on the border, so a long help can't push it out of sight
+        .title(Line::from("<C-Q>: Quit").right_aligned());
     let p = Paragraph::new(text)
         .block(b)
         .alignment(Alignment::Left)
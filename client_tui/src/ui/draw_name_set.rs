@@ -3,16 +3,16 @@ use crate::state::NameSetAction::*;
 use crate::state::TUIState;
 use ratatui::layout::Rect;
 use ratatui::prelude::Style;
-use ratatui::style::Stylize;
 use ratatui::widgets::BorderType::Rounded;
 use ratatui::widgets::{Block, Paragraph};
 use ratatui::Frame;
 use std::cell::Ref;
 
 pub(crate) fn draw_name_set(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let active_component = &state.ui_data.active_component;
     let border_style = if let NameSet(_) = active_component {
-        Style::new().green()
+        Style::new().fg(theme.accent)
     } else {
         Style::new()
     };
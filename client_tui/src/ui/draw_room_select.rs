@@ -10,8 +10,9 @@ use ratatui::Frame;
 use std::cell::Ref;
 
 pub(super) fn draw_room_select(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let border_style = if let ActiveComponent::RoomSelect = state.ui_data.active_component {
-        Style::new().green()
+        Style::new().fg(theme.accent)
     } else {
         Style::new()
     };
@@ -43,26 +44,26 @@ pub(super) fn draw_room_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
             '✔'.to_string()
         };
         let mut pending_style = if room.pending > 0 {
-            Style::new().yellow()
+            Style::new().fg(theme.pending)
         } else {
-            Style::new().green()
+            Style::new().fg(theme.ok)
         };
         let mut row_style = Style::new();
         let mut online_style = if room.net_reachable {
-            Style::new().on_green()
+            Style::new().bg(theme.ok)
         } else {
-            Style::new().on_red()
+            Style::new().bg(theme.error)
         };
         let mut registered_style = if room.registered_to {
-            Style::new().on_green()
+            Style::new().bg(theme.ok)
         } else {
             unread = "".to_string();
-            Style::new().on_red()
+            Style::new().bg(theme.error)
         };
 
         if let Some(r) = current_room {
             if r.id == room.id {
-                row_style = Style::new().black().on_green();
+                row_style = Style::new().black().bg(theme.accent);
                 pending_style = row_style;
                 online_style = Style::new();
                 registered_style = Style::new();
@@ -123,12 +124,12 @@ pub(super) fn draw_room_select(frame: &mut Frame, rect: Rect, state: &Ref<TUISta
     };
 
     let header_style_1 = if let RoomSelect = state.ui_data.active_component {
-        Style::new().black().on_green()
+        Style::new().black().bg(theme.accent)
     } else {
         Style::new().on_black()
     };
     let header_style_2 = if let RoomSelect = state.ui_data.active_component {
-        Style::new().black().on_light_green()
+        Style::new().black().bg(theme.accent_light)
     } else {
         Style::new()
     };
@@ -3,15 +3,15 @@ use crate::state::TUIState;
 use crate::ui::draw_alert::draw_alert;
 use ratatui::layout::Rect;
 use ratatui::prelude::Style;
-use ratatui::style::Stylize;
 use ratatui::widgets::BorderType::Rounded;
 use ratatui::widgets::{Block, Paragraph, Wrap};
 use ratatui::Frame;
 use std::cell::Ref;
 
 pub(super) fn draw_text_edit(frame: &mut Frame, rect: Rect, state: &Ref<TUIState>) {
+    let theme = &state.ui_data.theme;
     let border_style = if let TextEdit = state.ui_data.active_component {
-        Style::new().green()
+        Style::new().fg(theme.accent)
     } else {
         Style::new()
     };
@@ -19,12 +19,17 @@ pub(super) fn draw_text_edit(frame: &mut Frame, rect: Rect, state: &Ref<TUIState
     if state.ui_data.current_log.is_some() {
         let text = state.ui_data.text_message_in_edit.clone();
         let text_clone = text.clone();
+        let title = if state.ui_data.editing.is_some() {
+            "Edit your message!"
+        } else {
+            "Write something!"
+        };
 
         let mut editor = Paragraph::new(text).wrap(Wrap { trim: false }).block(
             Block::bordered()
                 .border_type(Rounded)
                 .border_style(border_style)
-                .title("Write something!"),
+                .title(title),
         );
 
         let line_count = if !text_clone.is_empty() {
@@ -44,6 +49,6 @@ pub(super) fn draw_text_edit(frame: &mut Frame, rect: Rect, state: &Ref<TUIState
                 .style(Style::new()),
             rect,
         );
-        draw_alert(frame, rect, "Select a friend first!");
+        draw_alert(frame, rect, theme, "Select a friend first!");
     }
 }
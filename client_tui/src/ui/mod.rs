@@ -2,16 +2,22 @@ mod chat_scroll_view;
 mod draw_alert;
 mod draw_chat_select;
 mod draw_chat_view;
+mod draw_drawing_edit;
+mod draw_edit_history;
+mod draw_file_prompt;
 mod draw_help_box;
 mod draw_name_set;
 mod draw_room_select;
 mod draw_text_edit;
 pub(crate) mod ui_utils;
 
-use crate::state::{ActiveComponent, TUIState};
+use crate::state::{ActiveComponent, FileAction, TUIState};
 use crate::ui::draw_alert::draw_alert;
 use crate::ui::draw_chat_select::draw_chat_select;
 use crate::ui::draw_chat_view::draw_chat_view;
+use crate::ui::draw_drawing_edit::{draw_drawing_edit, draw_drawing_status};
+use crate::ui::draw_edit_history::draw_edit_history;
+use crate::ui::draw_file_prompt::draw_file_prompt;
 use crate::ui::draw_help_box::draw_help_box;
 use crate::ui::draw_name_set::draw_name_set;
 use crate::ui::draw_room_select::draw_room_select;
@@ -26,7 +32,8 @@ use std::cmp::max;
 // left main h split (name select & room select)
 const ROOM_SELECT_MIN_HEIGHT: u16 = 10;
 const NAME_SET_HEIGHT: u16 = 3;
-const INFO_HEIGHT: u16 = 9;
+//fits the longest help, a message of ours selected in the chat view
+const INFO_HEIGHT: u16 = 12;
 
 const LEFT_MAIN_H_SPLIT_MIN_WIDTH: u16 = 32;
 const LEFT_MAIN_H_SPLIT_MIN_HEIGHT: u16 = ROOM_SELECT_MIN_HEIGHT + NAME_SET_HEIGHT + INFO_HEIGHT;
@@ -51,10 +58,14 @@ pub fn ui(frame: &mut Frame, state: Ref<TUIState>) {
     frame.render_widget(Block::new().style(Style::new().white()), area);
     let mut min_height = max(LEFT_MAIN_H_SPLIT_MIN_HEIGHT, CENTER_MAIN_H_SPLIT_MIN_HEIGHT);
     min_height = max(min_height, RIGHT_MAIN_H_SPLIT_MIN_HEIGHT);
-    if area.width < MIN_WIDTH || area.height < min_height {
+    if let Some(reason) = &state.backend_gone {
+        let text = format!("{} \nPress Ctrl-Q to quit.", reason);
+        draw_alert(frame, frame.area(), &state.ui_data.theme, &text);
+    } else if area.width < MIN_WIDTH || area.height < min_height {
         draw_alert(
             frame,
             frame.area(),
+            &state.ui_data.theme,
             "PictoRust needs a bigger window to display all its content. \nTry resizing!",
         );
     } else {
@@ -82,7 +93,27 @@ fn main_screen(frame: &mut Frame, state: Ref<TUIState>) {
     draw_name_set(frame, name_set_rect, &state);
     draw_room_select(frame, room_select_rect, &state);
     draw_chat_select(frame, chat_select_rect, &state);
-    draw_chat_view(frame, chat_view_rect, &state);
-    draw_text_edit(frame, text_edit_rect, &state);
+    match &state.ui_data.active_component {
+        ActiveComponent::DrawingEdit => {
+            draw_drawing_edit(frame, chat_view_rect, &state);
+            draw_drawing_status(frame, text_edit_rect, &state);
+        }
+        ActiveComponent::FilePrompt(action) => {
+            if let FileAction::LoadDrawing = action {
+                draw_drawing_edit(frame, chat_view_rect, &state);
+            } else {
+                draw_chat_view(frame, chat_view_rect, &state);
+            }
+            draw_file_prompt(frame, text_edit_rect, &state, action);
+        }
+        ActiveComponent::EditHistory => {
+            draw_chat_view(frame, chat_view_rect, &state);
+            draw_edit_history(frame, text_edit_rect, &state);
+        }
+        _ => {
+            draw_chat_view(frame, chat_view_rect, &state);
+            draw_text_edit(frame, text_edit_rect, &state);
+        }
+    }
     draw_help_box(frame, help_box_rect, &state);
 }
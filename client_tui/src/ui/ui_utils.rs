@@ -2,7 +2,13 @@ use crate::ui::{
     CENTER_MAIN_H_SPLIT_MIN_WIDTH, INFO_HEIGHT, LEFT_MAIN_H_SPLIT_MIN_WIDTH, NAME_SET_HEIGHT,
     RIGHT_MAIN_H_SPLIT_MIN_WIDTH, TEXT_EDIT_HEIGHT,
 };
+use chrono::{DateTime, Local};
+use client_lib::communication::{
+    MessageStatus, Reaction, SerializableColor, SerializableShape, TimeStamp,
+};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::Color;
+use ratatui::widgets::canvas::{Circle, Context, Line, Points, Rectangle};
 use ratatui::Frame;
 
 pub(crate) fn get_main_screen_rects(frame: &Frame) -> (Rect, Rect, Rect, Rect, Rect, Rect) {
@@ -37,3 +43,90 @@ pub(crate) fn get_main_screen_rects(frame: &Frame) -> (Rect, Rect, Rect, Rect, R
         right_v_split[1],
     )
 }
+
+pub(crate) fn rgb(c: &SerializableColor) -> Color {
+    Color::Rgb(c.r, c.g, c.b)
+}
+
+//draws the shapes on a canvas whose y axis is flipped, as it grows upwards
+//while the drawing's grows downwards
+pub(crate) fn paint_shapes(ctx: &mut Context, shapes: &[SerializableShape]) {
+    for shape in shapes {
+        let color = rgb(shape.color());
+        match shape {
+            SerializableShape::Rectangle(r) => {
+                let (min_x, _, _, max_y) = shape.bounds();
+                ctx.draw(&Rectangle {
+                    x: min_x,
+                    y: -max_y,
+                    width: r.width.abs(),
+                    height: r.height.abs(),
+                    color,
+                })
+            }
+            SerializableShape::Circle(c) => ctx.draw(&Circle {
+                x: c.x,
+                y: -c.y,
+                radius: c.radius.abs(),
+                color,
+            }),
+            SerializableShape::Line(l) => ctx.draw(&Line::new(l.x1, -l.y1, l.x2, -l.y2, color)),
+            SerializableShape::Point(p) => ctx.draw(&Points {
+                coords: &[(p.x, -p.y)],
+                color,
+            }),
+        }
+    }
+}
+
+//in the order the picker shows them, removing the reaction comes last
+pub(crate) static REACTIONS: [Reaction; 5] = [
+    Reaction::Like,
+    Reaction::Heart,
+    Reaction::Skull,
+    Reaction::Crying,
+    Reaction::Star,
+];
+pub(crate) const NO_REACTION_SYMBOL: &str = "✕";
+
+pub(crate) fn reaction_symbol(reaction: &Reaction) -> &'static str {
+    match reaction {
+        Reaction::Like => "👍",
+        Reaction::Heart => "❤",
+        Reaction::Skull => "💀",
+        Reaction::Crying => "😢",
+        Reaction::Star => "⭐",
+    }
+}
+
+//how far our message got, on its top border next to the time
+pub(crate) fn status_symbol(status: &MessageStatus) -> &'static str {
+    match status {
+        MessageStatus::SentToServer => "◷",
+        MessageStatus::ReceivedByServer => "✓",
+        MessageStatus::ReceivedByPeer => "✓✓",
+        MessageStatus::ReadByPeer => "✔✔",
+        MessageStatus::FailedToSend => "✗",
+        MessageStatus::MessageFromPeer => "",
+    }
+}
+
+//"now", "5m ago", "3h ago" or "2d ago"; a peer clock ahead of ours reads as now
+pub(crate) fn relative_time(time: TimeStamp, now: TimeStamp) -> String {
+    let elapsed = now.saturating_sub(time);
+    match elapsed {
+        0..=59 => "now".to_string(),
+        60..=3599 => format!("{}m ago", elapsed / 60),
+        3600..=86399 => format!("{}h ago", elapsed / 3600),
+        _ => format!("{}d ago", elapsed / 86400),
+    }
+}
+
+//the time on our clock face, none if it is unknown (0) or out of range
+pub(crate) fn local_time(time: TimeStamp) -> Option<DateTime<Local>> {
+    if time == 0 {
+        return None;
+    }
+    let time = i64::try_from(time).ok()?;
+    DateTime::from_timestamp(time, 0).map(|t| t.with_timezone(&Local))
+}
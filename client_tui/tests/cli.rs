@@ -0,0 +1,146 @@
+use client_lib::transport::Endpoint;
+use client_lib::ClientError;
+use client_tui::cli::{parse_args, CliArgs, CliCommand};
+use client_tui::config::TuiConfig;
+use ratatui::style::Color;
+use std::io::Read;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+
+fn parse(args: &[&str]) -> Result<CliCommand, ClientError> {
+    parse_args(args.iter().map(|a| a.to_string()))
+}
+
+fn run_args(args: &[&str]) -> CliArgs {
+    match parse(args) {
+        Ok(CliCommand::Run(args)) => args,
+        other => panic!("expected arguments to run with, got {:?}", other),
+    }
+}
+
+fn localhost(port: u16) -> Endpoint {
+    Endpoint::Tcp(([127, 0, 0, 1], port).into())
+}
+
+#[test]
+fn endpoint_is_given_positionally_or_by_option() {
+    assert_eq!(run_args(&["4000"]).endpoint, localhost(4000));
+    assert_eq!(run_args(&["--port", "4000"]).endpoint, localhost(4000));
+    assert_eq!(run_args(&["-p", "4000"]).endpoint, localhost(4000));
+    assert_eq!(run_args(&["--port=4000"]).endpoint, localhost(4000));
+    assert_eq!(run_args(&["tcp:127.0.0.1:4000"]).endpoint, localhost(4000));
+    #[cfg(unix)]
+    {
+        let socket = Endpoint::Unix(PathBuf::from("/tmp/tui.sock"));
+        assert_eq!(run_args(&["--socket", "/tmp/tui.sock"]).endpoint, socket);
+        assert_eq!(run_args(&["-s", "/tmp/tui.sock"]).endpoint, socket);
+        assert_eq!(run_args(&["unix:/tmp/tui.sock"]).endpoint, socket);
+    }
+}
+
+#[test]
+fn files_are_optional() {
+    let args = run_args(&["4000"]);
+    assert_eq!(args.token_file, None);
+    assert_eq!(args.log_file, None);
+    assert_eq!(args.config, None);
+
+    let args = run_args(&[
+        "--token-file",
+        "-",
+        "--log=/tmp/chat.log",
+        "-c",
+        "theme.conf",
+        "4000",
+    ]);
+    assert_eq!(args.token_file, Some(PathBuf::from("-")));
+    assert_eq!(args.log_file, Some(PathBuf::from("/tmp/chat.log")));
+    assert_eq!(args.config, Some(PathBuf::from("theme.conf")));
+}
+
+#[test]
+fn help_and_version_win() {
+    assert_eq!(parse(&["4000", "--help"]).unwrap(), CliCommand::Help);
+    assert_eq!(parse(&["-h"]).unwrap(), CliCommand::Help);
+    assert_eq!(parse(&["-V"]).unwrap(), CliCommand::Version);
+}
+
+#[test]
+fn bad_arguments_are_usage_errors() {
+    for args in [
+        &[][..],
+        &["4000", "--port", "4001"],
+        &["--port"],
+        &["--port", "many"],
+        &["--colour", "4000"],
+    ] {
+        let error = parse(args).unwrap_err();
+        assert!(matches!(error, ClientError::UsageError(_)), "{:?}", args);
+        assert_eq!(error.exit_code(), 2);
+    }
+    assert!(matches!(
+        parse(&["carrier-pigeon:home"]),
+        Err(ClientError::InvalidEndpoint(_))
+    ));
+}
+
+#[test]
+fn config_sets_the_theme() {
+    let config = "
+        # colors are names or hex
+        accent = blue
+        pending=#ff8800
+    "
+    .parse::<TuiConfig>()
+    .unwrap();
+    assert_eq!(config.theme.accent, Color::Blue);
+    assert_eq!(config.theme.pending, Color::Rgb(0xff, 0x88, 0x00));
+    assert_eq!(config.theme.error, TuiConfig::default().theme.error);
+
+    for invalid in ["accent", "accent = not-a-color", "font = mono"] {
+        assert!(matches!(
+            invalid.parse::<TuiConfig>(),
+            Err(ClientError::InvalidConfig(_))
+        ));
+    }
+}
+
+fn chat_tui(args: &[&str]) -> Option<i32> {
+    Command::new(env!("CARGO_BIN_EXE_chat-tui"))
+        .args(args)
+        .env_remove("PICTORUST_TOKEN")
+        .output()
+        .unwrap()
+        .status
+        .code()
+}
+
+#[test]
+fn binary_exit_codes_follow_the_error() {
+    assert_eq!(chat_tui(&["--help"]), Some(0));
+    assert_eq!(chat_tui(&[]), Some(2));
+    assert_eq!(
+        chat_tui(&["4000", "--config", "/nonexistent/theme.conf"]),
+        Some(3)
+    );
+
+    //nobody listens on a port just released
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    assert_eq!(chat_tui(&[&port.to_string()]), Some(3));
+
+    //a backend hanging up on the hello, as it does without the right token
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+    let backend = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = stream.read(&mut [0u8; 64]);
+    });
+    assert_eq!(chat_tui(&[&port]), Some(4));
+    backend.join().unwrap();
+}
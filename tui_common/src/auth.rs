@@ -0,0 +1,55 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::SystemTime;
+
+//where the backend puts the token for the TUI it spawns, argv is visible to every local user
+pub const TOKEN_ENV: &str = "PICTORUST_TOKEN";
+
+//what a side proves or asks for in the handshake
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Auth<'a> {
+    //no token is sent nor checked
+    Open,
+    //the token is sent in our hello
+    Present(&'a str),
+    //the other side's hello must carry this token, or the stream is closed
+    Require(&'a str),
+}
+
+//a new random token, 32 bytes written in hex
+pub fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    if !os_random(&mut bytes) {
+        //keyed with random per-process keys, unpredictable enough without the os
+        for chunk in bytes.chunks_mut(8) {
+            let mut hasher = RandomState::new().build_hasher();
+            if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+                hasher.write_u128(elapsed.as_nanos());
+            }
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(unix)]
+fn os_random(bytes: &mut [u8]) -> bool {
+    use std::io::Read;
+    std::fs::File::open("/dev/urandom")
+        .and_then(|mut file| file.read_exact(bytes))
+        .is_ok()
+}
+
+#[cfg(not(unix))]
+fn os_random(_bytes: &mut [u8]) -> bool {
+    false
+}
+
+//compares in a time that does not depend on where the tokens differ
+pub(crate) fn tokens_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -0,0 +1,69 @@
+use crate::ClientError;
+use crate::ClientError::{Decode, Encode};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+//turns messages into bytes and back
+pub trait Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ClientError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ClientError>;
+}
+
+//readable, used by default and understood by every build
+#[derive(Debug, Copy, Clone, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ClientError> {
+        serde_json::to_vec(value).map_err(|e| Encode(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ClientError> {
+        serde_json::from_slice(bytes).map_err(|e| Decode(e.to_string()))
+    }
+}
+
+//compact, worth it for drawings made of many shapes
+#[derive(Debug, Copy, Clone, Default)]
+pub struct BinaryCodec;
+
+impl Codec for BinaryCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ClientError> {
+        bincode::serialize(value).map_err(|e| Encode(e.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ClientError> {
+        //lengths inside the frame can't make it allocate more than the frame itself
+        bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(bytes.len() as u64)
+            .deserialize(bytes)
+            .map_err(|e| Decode(e.to_string()))
+    }
+}
+
+//the codec picked for a connection during the handshake
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum WireCodec {
+    #[default]
+    Json,
+    Binary,
+}
+
+impl Codec for WireCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, ClientError> {
+        match self {
+            WireCodec::Json => JsonCodec.encode(value),
+            WireCodec::Binary => BinaryCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ClientError> {
+        match self {
+            WireCodec::Json => JsonCodec.decode(bytes),
+            WireCodec::Binary => BinaryCodec.decode(bytes),
+        }
+    }
+}
@@ -1,15 +1,32 @@
+use crate::auth::{tokens_match, Auth};
+use crate::codec::{Codec, JsonCodec, WireCodec};
+use crate::transport::Transport;
 use crate::ClientError;
-use crate::ClientError::{SerializationError, StreamError};
+use crate::ClientError::{
+    ConnectionClosed, FrameTooLarge, HandshakeError, Io, Unauthorized, VersionMismatch,
+};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::fmt::{Debug, Formatter};
+use std::io::{ErrorKind, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use wg_2024::network::NodeId;
 
+//bumped every time TUIEvent or TUICommand change in an incompatible way
+pub const PROTOCOL_VERSION: u32 = 4;
+//how long each side waits for the other's hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+//default limit for a single frame, drawings included
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+//a hello is tiny, anything bigger is not one
+const MAX_HELLO_SIZE: usize = 64 * 1024;
+
 pub type ChatServerID = NodeId;
 pub type ChatClientID = NodeId;
 pub type MessageID = u64;
-pub type TimeStamp = u32;
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+//seconds since the Unix epoch, on the clock of whoever took it
+pub type TimeStamp = u64;
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Reaction {
     Like,
     Heart,
@@ -17,21 +34,43 @@ pub enum Reaction {
     Crying,
     Star,
 }
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MessageStatus {
     SentToServer,
     ReceivedByServer,
     ReceivedByPeer,
     ReadByPeer,
     MessageFromPeer,
+    FailedToSend,
+}
+
+impl MessageStatus {
+    //how far the message got; a failure is undone by any sign it went through
+    fn progress(&self) -> u8 {
+        match self {
+            MessageStatus::SentToServer | MessageStatus::MessageFromPeer => 0,
+            MessageStatus::FailedToSend => 1,
+            MessageStatus::ReceivedByServer => 2,
+            MessageStatus::ReceivedByPeer => 3,
+            MessageStatus::ReadByPeer => 4,
+        }
+    }
+
+    //acks and receipts can arrive in any order, a late one must not take the status back
+    pub fn replaces(&self, old: Option<MessageStatus>) -> bool {
+        old.is_none_or(|old| self.progress() >= old.progress())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TUIEvent {
     SendMessage(ChatServerID, ChatClientID, MessageID, MessageContent),
+    //replaces the content of a message already sent
+    EditMessage(ChatServerID, ChatClientID, MessageID, MessageContent),
     ReadMessage(ChatServerID, ChatClientID, MessageID),
     DeleteMessage(ChatServerID, ChatClientID, MessageID),
-    ReactToMessage(ChatServerID, ChatClientID, MessageID, Reaction),
+    //none takes the reaction back
+    ReactToMessage(ChatServerID, ChatClientID, MessageID, Option<Reaction>),
 
     SetName(String),
 
@@ -42,18 +81,66 @@ pub enum TUIEvent {
     Dead,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MessageContent {
     TextMessage(String),
     Drawing(Drawing),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Drawing {
     shapes: Vec<SerializableShape>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+//coordinates grow rightwards and downwards, as on a screen
+impl Drawing {
+    pub fn new() -> Self {
+        Drawing::default()
+    }
+
+    //shapes are drawn in order, later ones on top
+    pub fn with_shape(mut self, shape: SerializableShape) -> Self {
+        self.shapes.push(shape);
+        self
+    }
+
+    pub fn push(&mut self, shape: SerializableShape) {
+        self.shapes.push(shape);
+    }
+
+    //removes the last shape drawn
+    pub fn pop(&mut self) -> Option<SerializableShape> {
+        self.shapes.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.shapes.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    pub fn shapes(&self) -> &[SerializableShape] {
+        &self.shapes
+    }
+
+    //(min x, min y, max x, max y) covered by the shapes, none for an empty drawing
+    pub fn bounds(&self) -> Option<(f64, f64, f64, f64)> {
+        self.shapes
+            .iter()
+            .map(SerializableShape::bounds)
+            .reduce(|a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)))
+    }
+}
+
+impl From<Vec<SerializableShape>> for Drawing {
+    fn from(shapes: Vec<SerializableShape>) -> Self {
+        Drawing { shapes }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SerializableShape {
     Rectangle(SerializableRectangle),
     Circle(SerializableCircle),
@@ -61,13 +148,54 @@ pub enum SerializableShape {
     Point(SerializablePoint),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl SerializableShape {
+    pub fn color(&self) -> &SerializableColor {
+        match self {
+            SerializableShape::Rectangle(r) => &r.color,
+            SerializableShape::Circle(c) => &c.color,
+            SerializableShape::Line(l) => &l.color,
+            SerializableShape::Point(p) => &p.color,
+        }
+    }
+
+    //(min x, min y, max x, max y), negative sizes are drawn towards the origin
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        match self {
+            SerializableShape::Rectangle(r) => (
+                r.x.min(r.x + r.width),
+                r.y.min(r.y + r.height),
+                r.x.max(r.x + r.width),
+                r.y.max(r.y + r.height),
+            ),
+            SerializableShape::Circle(c) => {
+                let radius = c.radius.abs();
+                (c.x - radius, c.y - radius, c.x + radius, c.y + radius)
+            }
+            SerializableShape::Line(l) => (
+                l.x1.min(l.x2),
+                l.y1.min(l.y2),
+                l.x1.max(l.x2),
+                l.y1.max(l.y2),
+            ),
+            SerializableShape::Point(p) => (p.x, p.y, p.x, p.y),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializableColor {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+
+impl SerializableColor {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        SerializableColor { r, g, b }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializableRectangle {
     pub x: f64,
     pub y: f64,
@@ -75,14 +203,14 @@ pub struct SerializableRectangle {
     pub height: f64,
     pub color: SerializableColor,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializableCircle {
     pub x: f64,
     pub y: f64,
     pub radius: f64,
     pub color: SerializableColor,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializableLine {
     pub x1: f64,
     pub y1: f64,
@@ -90,14 +218,36 @@ pub struct SerializableLine {
     pub y2: f64,
     pub color: SerializableColor,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SerializablePoint {
     pub x: f64,
     pub y: f64,
     pub color: SerializableColor,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+//optional features a side supports, only the shared ones are used
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    Drawings,
+    Reactions,
+    //frames are encoded with the binary codec instead of json
+    BinaryCodec,
+    //anything added by a newer build
+    #[serde(other)]
+    Unknown,
+}
+
+//first frame sent by both sides, before any event or command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub capabilities: Vec<Capability>,
+    //proves the frontend was started by the backend, missing in older hellos
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TUICommand {
     // Name
     UpdateName(String),
@@ -108,11 +258,20 @@ pub enum TUICommand {
 
     // ChatLog
     UpdatePeerName(ChatServerID, ChatClientID, Option<String>),
-    UpdatePeerLastSeen(ChatServerID, ChatClientID),
+    //when the backend last heard from the peer
+    UpdatePeerLastSeen(ChatServerID, ChatClientID, TimeStamp),
     UpdatePeerStatus(ChatServerID, ChatClientID, bool),
 
     // Message
-    UpdateMessageContent(ChatServerID, ChatClientID, MessageID, MessageContent),
+    //the time is when the sender sent the message, or the edit;
+    //older builds don't send it, the time it arrives stands in for it
+    UpdateMessageContent(
+        ChatServerID,
+        ChatClientID,
+        MessageID,
+        MessageContent,
+        #[serde(default = "now")] TimeStamp,
+    ),
     UpdateMessageStatus(ChatServerID, ChatClientID, MessageID, MessageStatus),
     UpdateMessageReaction(ChatServerID, ChatClientID, MessageID, Option<Reaction>),
     DeleteMessage(ChatClientID, ChatClientID, MessageID),
@@ -120,27 +279,187 @@ pub enum TUICommand {
     Kill,
 }
 
-pub fn send_message<T: Serialize>(stream: &mut TcpStream, message: T) -> Result<(), ClientError> {
-    let serialized = serde_json::to_string(&message).map_err(|_| SerializationError)?;
-    let len = serialized.len() as u32;
-    stream
-        .write_all(&len.to_be_bytes())
-        .map_err(|_| StreamError)?;
-    stream
-        .write_all(serialized.as_bytes())
-        .map_err(|_| StreamError)?;
-    stream.flush().map_err(|_| StreamError)?;
+//a clock set before 1970 counts as the epoch itself
+pub fn now() -> TimeStamp {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn send_message<T: Serialize>(stream: &mut dyn Write, message: T) -> Result<(), ClientError> {
+    send_message_with(stream, &JsonCodec, message, MAX_FRAME_SIZE)
+}
+
+pub fn receive_message<T: DeserializeOwned>(stream: &mut dyn Read) -> Result<T, ClientError> {
+    receive_message_with(stream, &JsonCodec, MAX_FRAME_SIZE)
+}
+
+pub fn send_message_with<C: Codec, T: Serialize>(
+    stream: &mut dyn Write,
+    codec: &C,
+    message: T,
+    max_frame_size: usize,
+) -> Result<(), ClientError> {
+    let serialized = codec.encode(&message)?;
+    let len = serialized.len();
+    if len > max_frame_size || len > u32::MAX as usize {
+        return Err(FrameTooLarge(len, max_frame_size));
+    }
+    stream.write_all(&(len as u32).to_be_bytes()).map_err(Io)?;
+    stream.write_all(&serialized).map_err(Io)?;
+    stream.flush().map_err(Io)?;
     Ok(())
 }
 
-pub fn receive_message<T: for<'de> Deserialize<'de>>(
-    stream: &mut TcpStream,
+pub fn receive_message_with<C: Codec, T: DeserializeOwned>(
+    stream: &mut dyn Read,
+    codec: &C,
+    max_frame_size: usize,
 ) -> Result<T, ClientError> {
     let mut len_bytes = [0u8; 4];
-    stream.read_exact(&mut len_bytes).map_err(|_| StreamError)?;
+    read_header(stream, &mut len_bytes)?;
     let len = u32::from_be_bytes(len_bytes) as usize;
+    //checked before allocating, the header may be corrupt or hostile
+    if len > max_frame_size {
+        return Err(FrameTooLarge(len, max_frame_size));
+    }
     let mut buffer = vec![0u8; len];
-    stream.read_exact(&mut buffer).map_err(|_| StreamError)?;
+    stream.read_exact(&mut buffer).map_err(Io)?;
+
+    codec.decode(&buffer)
+}
+
+//reads a frame header, telling a stream closed between frames from one cut mid frame
+fn read_header(stream: &mut dyn Read, header: &mut [u8]) -> Result<(), ClientError> {
+    let mut read = 0;
+    while read < header.len() {
+        match stream.read(&mut header[read..]) {
+            Ok(0) if read == 0 => return Err(ConnectionClosed),
+            Ok(0) => return Err(Io(ErrorKind::UnexpectedEof.into())),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(Io(e)),
+        }
+    }
+    Ok(())
+}
+
+//a stream to the other side, framed with the codec agreed on in the handshake
+pub struct Connection {
+    stream: Box<dyn Transport>,
+    codec: WireCodec,
+    capabilities: Vec<Capability>,
+    max_frame_size: usize,
+}
+
+impl Connection {
+    pub fn send<T: Serialize>(&mut self, message: T) -> Result<(), ClientError> {
+        send_message_with(&mut self.stream, &self.codec, message, self.max_frame_size)
+    }
+
+    pub fn receive<T: DeserializeOwned>(&mut self) -> Result<T, ClientError> {
+        receive_message_with(&mut self.stream, &self.codec, self.max_frame_size)
+    }
 
-    serde_json::from_slice(&buffer).map_err(|_| SerializationError)
+    pub fn try_clone(&self) -> Result<Connection, ClientError> {
+        Ok(Connection {
+            stream: self.stream.try_clone_boxed().map_err(Io)?,
+            codec: self.codec,
+            capabilities: self.capabilities.clone(),
+            max_frame_size: self.max_frame_size,
+        })
+    }
+
+    //frames bigger than this are refused in both directions
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    pub fn codec(&self) -> WireCodec {
+        self.codec
+    }
+
+    //the capabilities both sides support
+    pub fn capabilities(&self) -> &[Capability] {
+        &self.capabilities
+    }
+
+    //closes both directions, the other side sees the stream end
+    pub fn shutdown(&self) {
+        self.stream.close();
+    }
+}
+
+impl Debug for Connection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("codec", &self.codec)
+            .field("capabilities", &self.capabilities)
+            .field("max_frame_size", &self.max_frame_size)
+            .finish_non_exhaustive()
+    }
+}
+
+//exchanges hellos on a fresh stream and sets it up with what both sides support
+pub fn handshake(
+    mut stream: Box<dyn Transport>,
+    capabilities: &[Capability],
+    auth: Auth,
+) -> Result<Connection, ClientError> {
+    let hello = Hello {
+        protocol_version: PROTOCOL_VERSION,
+        capabilities: capabilities.to_vec(),
+        token: match auth {
+            Auth::Present(token) => Some(token.to_string()),
+            _ => None,
+        },
+    };
+
+    let other = if let Auth::Require(token) = auth {
+        //nothing is told to the other side before it proves who it is
+        let other = receive_hello(&mut stream)?;
+        if !other
+            .token
+            .as_deref()
+            .is_some_and(|other| tokens_match(other, token))
+        {
+            stream.close();
+            return Err(Unauthorized);
+        }
+        send_message(&mut stream, hello)?;
+        other
+    } else {
+        send_message(&mut stream, hello)?;
+        receive_hello(&mut stream)?
+    };
+
+    if other.protocol_version != PROTOCOL_VERSION {
+        return Err(VersionMismatch(PROTOCOL_VERSION, other.protocol_version));
+    }
+    let capabilities = capabilities
+        .iter()
+        .filter(|c| other.capabilities.contains(c))
+        .copied()
+        .collect::<Vec<_>>();
+    let codec = if capabilities.contains(&Capability::BinaryCodec) {
+        WireCodec::Binary
+    } else {
+        WireCodec::Json
+    };
+    Ok(Connection {
+        stream,
+        codec,
+        capabilities,
+        max_frame_size: MAX_FRAME_SIZE,
+    })
+}
+
+fn receive_hello(stream: &mut Box<dyn Transport>) -> Result<Hello, ClientError> {
+    stream
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .map_err(Io)?;
+    let hello = receive_message_with::<_, Hello>(stream, &JsonCodec, MAX_HELLO_SIZE)
+        .map_err(|e| HandshakeError(Box::new(e)));
+    stream.set_read_timeout(None).map_err(Io)?;
+    hello
 }
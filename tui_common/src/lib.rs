@@ -1,5 +1,14 @@
+pub mod auth;
+pub mod codec;
 pub mod communication;
+pub mod pnm;
+pub mod svg;
 pub mod sys;
+pub mod transport;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
 
 #[derive(Debug)]
 pub enum ClientError {
@@ -11,4 +20,93 @@ pub enum ClientError {
     TUICommandHandlingError,
     UIError,
     CrossTermError,
+    //the other side did not send a valid hello in time, (why it could not be read)
+    HandshakeError(Box<ClientError>),
+    //(our protocol version, their protocol version)
+    VersionMismatch(u32, u32),
+    //the other side closed the stream between two frames
+    ConnectionClosed,
+    //(frame size, max frame size)
+    FrameTooLarge(usize, usize),
+    Io(io::Error),
+    Encode(String),
+    Decode(String),
+    //the other side did not present the expected token
+    Unauthorized,
+    //(what is wrong with the command line)
+    UsageError(String),
+    //(why the config file was refused)
+    InvalidConfig(String),
+    //an endpoint that is neither "tcp:<address>" nor "unix:<path>"
+    InvalidEndpoint(String),
+    //(why the file could not be read as a drawing)
+    InvalidDrawing(String),
+}
+
+impl ClientError {
+    //what a binary failing with this error exits with
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ClientError::EnvError
+            | ClientError::UsageError(_)
+            | ClientError::InvalidEndpoint(_)
+            | ClientError::InvalidConfig(_)
+            | ClientError::InvalidDrawing(_) => 2,
+            ClientError::ListenerError
+            | ClientError::StreamError
+            | ClientError::ConnectionClosed
+            | ClientError::Io(_) => 3,
+            ClientError::HandshakeError(_)
+            | ClientError::VersionMismatch(..)
+            | ClientError::FrameTooLarge(..)
+            | ClientError::SerializationError
+            | ClientError::Encode(_)
+            | ClientError::Decode(_) => 4,
+            ClientError::Unauthorized => 5,
+            ClientError::UIError | ClientError::CrossTermError => 6,
+            ClientError::LockError | ClientError::TUICommandHandlingError => 1,
+        }
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::HandshakeError(e) => {
+                write!(f, "the other side did not complete the handshake: {}", e)
+            }
+            ClientError::VersionMismatch(ours, theirs) => write!(
+                f,
+                "protocol version mismatch: this build speaks {}, the other side {}",
+                ours, theirs
+            ),
+            ClientError::ConnectionClosed => write!(f, "the other side closed the connection"),
+            ClientError::FrameTooLarge(size, max) => {
+                write!(
+                    f,
+                    "frame of {} bytes exceeds the limit of {} bytes",
+                    size, max
+                )
+            }
+            ClientError::Io(e) => write!(f, "i/o error: {}", e),
+            ClientError::Encode(e) => write!(f, "could not encode message: {}", e),
+            ClientError::Decode(e) => write!(f, "could not decode message: {}", e),
+            ClientError::Unauthorized => write!(f, "the other side did not present a valid token"),
+            ClientError::UsageError(s) => write!(f, "{}", s),
+            ClientError::InvalidConfig(s) => write!(f, "invalid config: {}", s),
+            ClientError::InvalidEndpoint(s) => write!(f, "invalid endpoint: {}", s),
+            ClientError::InvalidDrawing(s) => write!(f, "invalid drawing: {}", s),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientError::Io(e) => Some(e),
+            ClientError::HandshakeError(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
 }
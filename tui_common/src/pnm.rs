@@ -0,0 +1,131 @@
+use crate::communication::{Drawing, SerializableColor, SerializableShape};
+use std::f64::consts::PI;
+
+//what the shapes are drawn on
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+//pixels left free around the drawing
+const MARGIN: f64 = 1.0;
+
+//the height that keeps the drawing's proportions at the given width
+pub fn raster_size(drawing: &Drawing, width: u32) -> (u32, u32) {
+    let (min_x, min_y, max_x, max_y) = drawing.bounds().unwrap_or((0.0, 0.0, 1.0, 1.0));
+    let w = (max_x - min_x).max(f64::EPSILON);
+    let h = max_y - min_y;
+    let free_w = (width as f64 - 1.0 - 2.0 * MARGIN).max(0.0);
+    let height = (free_w * h / w + 1.0 + 2.0 * MARGIN).round();
+    //a vertical line would be endless
+    (width, height.clamp(1.0, width.max(1) as f64 * 4.0) as u32)
+}
+
+//binary portable pixmap, the drawing scaled to fit the image
+pub fn to_ppm(drawing: &Drawing, width: u32, height: u32) -> Vec<u8> {
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for pixel in rasterize(drawing, width, height) {
+        ppm.extend(pixel);
+    }
+    ppm
+}
+
+//binary portable graymap, same as the pixmap but in shades of grey
+pub fn to_pgm(drawing: &Drawing, width: u32, height: u32) -> Vec<u8> {
+    let mut pgm = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+    pgm.extend(rasterize(drawing, width, height).iter().map(luma));
+    pgm
+}
+
+//the grey a pixel becomes in a graymap, rec. 601 weights
+pub fn luma(pixel: &[u8; 3]) -> u8 {
+    let [r, g, b] = pixel.map(|c| c as u32);
+    ((299 * r + 587 * g + 114 * b + 500) / 1000) as u8
+}
+
+//(scale, x offset, y offset) taking drawing coordinates to pixels
+fn transform(drawing: &Drawing, width: u32, height: u32) -> (f64, f64, f64) {
+    let (min_x, min_y, max_x, max_y) = drawing.bounds().unwrap_or((0.0, 0.0, 0.0, 0.0));
+    let (w, h) = (max_x - min_x, max_y - min_y);
+    let free_w = (width as f64 - 1.0 - 2.0 * MARGIN).max(0.0);
+    let free_h = (height as f64 - 1.0 - 2.0 * MARGIN).max(0.0);
+    let scale = match (w > 0.0, h > 0.0) {
+        (true, true) => (free_w / w).min(free_h / h),
+        (true, false) => free_w / w,
+        (false, true) => free_h / h,
+        (false, false) => 1.0,
+    };
+    //centred on both axes
+    let x = MARGIN + (free_w - w * scale) / 2.0 - min_x * scale;
+    let y = MARGIN + (free_h - h * scale) / 2.0 - min_y * scale;
+    (scale, x, y)
+}
+
+//one pixel per entry, row by row from the top left
+fn rasterize(drawing: &Drawing, width: u32, height: u32) -> Vec<[u8; 3]> {
+    let mut canvas = Canvas {
+        pixels: vec![BACKGROUND; width as usize * height as usize],
+        width: width as i64,
+        height: height as i64,
+        transform: transform(drawing, width, height),
+    };
+    for shape in drawing.shapes() {
+        canvas.draw(shape);
+    }
+    canvas.pixels
+}
+
+struct Canvas {
+    pixels: Vec<[u8; 3]>,
+    width: i64,
+    height: i64,
+    transform: (f64, f64, f64),
+}
+
+impl Canvas {
+    fn draw(&mut self, shape: &SerializableShape) {
+        let color = shape.color();
+        match shape {
+            SerializableShape::Rectangle(_) => {
+                let (x1, y1, x2, y2) = shape.bounds();
+                self.line((x1, y1), (x2, y1), color);
+                self.line((x2, y1), (x2, y2), color);
+                self.line((x2, y2), (x1, y2), color);
+                self.line((x1, y2), (x1, y1), color);
+            }
+            SerializableShape::Circle(c) => {
+                let (cx, cy) = self.to_pixel(c.x, c.y);
+                let radius = c.radius.abs() * self.transform.0;
+                let steps = (2.0 * PI * radius).ceil().max(8.0) as u32;
+                for i in 0..steps {
+                    let angle = 2.0 * PI * i as f64 / steps as f64;
+                    self.plot(cx + radius * angle.cos(), cy + radius * angle.sin(), color);
+                }
+            }
+            SerializableShape::Line(l) => self.line((l.x1, l.y1), (l.x2, l.y2), color),
+            SerializableShape::Point(p) => {
+                let (x, y) = self.to_pixel(p.x, p.y);
+                self.plot(x, y, color);
+            }
+        }
+    }
+
+    fn to_pixel(&self, x: f64, y: f64) -> (f64, f64) {
+        let (scale, dx, dy) = self.transform;
+        (x * scale + dx, y * scale + dy)
+    }
+
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), color: &SerializableColor) {
+        let (x1, y1) = self.to_pixel(from.0, from.1);
+        let (x2, y2) = self.to_pixel(to.0, to.1);
+        let steps = (x2 - x1).abs().max((y2 - y1).abs()).ceil().max(1.0) as u32;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            self.plot(x1 + (x2 - x1) * t, y1 + (y2 - y1) * t, color);
+        }
+    }
+
+    //pixels outside the image are dropped
+    fn plot(&mut self, x: f64, y: f64, color: &SerializableColor) {
+        let (x, y) = (x.round() as i64, y.round() as i64);
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = [color.r, color.g, color.b];
+        }
+    }
+}
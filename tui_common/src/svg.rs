@@ -0,0 +1,265 @@
+use crate::communication::{
+    Drawing, SerializableCircle, SerializableColor, SerializableLine, SerializablePoint,
+    SerializableRectangle, SerializableShape,
+};
+use crate::ClientError;
+use crate::ClientError::InvalidDrawing;
+
+//space left around the shapes in the viewBox, so strokes on the edge are not cut
+const MARGIN: f64 = 1.0;
+//points have no svg element of their own, they are tagged dots
+const POINT_CLASS: &str = "point";
+const POINT_RADIUS: f64 = 0.5;
+
+//shapes are outlined, as in the TUI; points become small filled circles
+pub fn to_svg(drawing: &Drawing) -> String {
+    let (min_x, min_y, max_x, max_y) = drawing.bounds().unwrap_or((0.0, 0.0, 0.0, 0.0));
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min_x - MARGIN,
+        min_y - MARGIN,
+        max_x - min_x + 2.0 * MARGIN,
+        max_y - min_y + 2.0 * MARGIN
+    );
+    for shape in drawing.shapes() {
+        svg.push_str("  ");
+        svg.push_str(&element(shape));
+        svg.push('\n');
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn element(shape: &SerializableShape) -> String {
+    let color = hex(shape.color());
+    match shape {
+        //svg sizes can't be negative, the rectangle is turned to grow from its top left corner
+        SerializableShape::Rectangle(r) => {
+            let (x, y, _, _) = shape.bounds();
+            format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\"/>",
+                x,
+                y,
+                r.width.abs(),
+                r.height.abs(),
+                color
+            )
+        }
+        SerializableShape::Circle(c) => format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\"/>",
+            c.x,
+            c.y,
+            c.radius.abs(),
+            color
+        ),
+        SerializableShape::Line(l) => format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"/>",
+            l.x1, l.y1, l.x2, l.y2, color
+        ),
+        SerializableShape::Point(p) => format!(
+            "<circle class=\"{}\" cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>",
+            POINT_CLASS, p.x, p.y, POINT_RADIUS, color
+        ),
+    }
+}
+
+fn hex(c: &SerializableColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+//reads rect, circle and line elements, the only ones a drawing can hold;
+//any other shape or a transform is refused rather than silently lost
+pub fn from_svg(svg: &str) -> Result<Drawing, ClientError> {
+    let mut drawing = Drawing::new();
+    let mut seen_svg = false;
+    let mut rest = svg;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            let end = rest
+                .find("-->")
+                .ok_or_else(|| InvalidDrawing("unterminated comment".to_string()))?;
+            rest = &rest[end + 3..];
+            continue;
+        }
+        let end = tag_end(rest)?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        //declarations, doctypes and closing tags carry nothing
+        if tag.starts_with(['?', '!', '/']) {
+            continue;
+        }
+        let tag = tag.strip_suffix('/').unwrap_or(tag);
+        let (name, attributes) = tag
+            .split_once(|c: char| c.is_whitespace())
+            .unwrap_or((tag, ""));
+        let attributes = parse_attributes(attributes)?;
+        if name == "svg" {
+            seen_svg = true;
+            continue;
+        }
+        if !seen_svg {
+            return Err(InvalidDrawing(format!("<{}> outside of <svg>", name)));
+        }
+        if find(&attributes, "transform").is_some() {
+            return Err(InvalidDrawing(format!(
+                "transform on <{}> is not supported",
+                name
+            )));
+        }
+        match name {
+            "rect" | "circle" | "line" => drawing.push(shape(name, &attributes)?),
+            "g" | "title" | "desc" | "metadata" => {}
+            //editor metadata, e.g. sodipodi:namedview
+            _ if name.contains(':') => {}
+            _ => return Err(InvalidDrawing(format!("unsupported element <{}>", name))),
+        }
+    }
+    if !seen_svg {
+        return Err(InvalidDrawing("not an svg document".to_string()));
+    }
+    Ok(drawing)
+}
+
+//where the tag starting the string ends, skipping '>' in quoted values
+fn tag_end(s: &str) -> Result<usize, ClientError> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Ok(i),
+            _ => {}
+        }
+    }
+    Err(InvalidDrawing("unterminated tag".to_string()))
+}
+
+//name="value" pairs, the properties in a style attribute override the plain ones
+fn parse_attributes(s: &str) -> Result<Vec<(String, String)>, ClientError> {
+    let mut attributes = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = rest
+            .split_once('=')
+            .ok_or_else(|| InvalidDrawing(format!("bad attribute \"{}\"", rest)))?;
+        let after = after.trim_start();
+        let quote = after
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| InvalidDrawing(format!("unquoted value for {}", name.trim())))?;
+        let after = &after[1..];
+        let end = after
+            .find(quote)
+            .ok_or_else(|| InvalidDrawing(format!("unterminated value for {}", name.trim())))?;
+        attributes.push((name.trim().to_string(), after[..end].to_string()));
+        rest = after[end + 1..].trim_start();
+    }
+    if let Some(style) = find(&attributes, "style").map(|s| s.to_string()) {
+        for property in style.split(';') {
+            if let Some((name, value)) = property.split_once(':') {
+                attributes.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+//the last value given to the attribute
+fn find<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .rev()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+//missing lengths are 0, as in svg
+fn number(attributes: &[(String, String)], name: &str) -> Result<f64, ClientError> {
+    match find(attributes, name) {
+        None => Ok(0.0),
+        Some(value) => {
+            let value = value.trim();
+            value
+                .strip_suffix("px")
+                .unwrap_or(value)
+                .parse()
+                .map_err(|_| InvalidDrawing(format!("bad {} \"{}\"", name, value)))
+        }
+    }
+}
+
+fn shape(name: &str, attributes: &[(String, String)]) -> Result<SerializableShape, ClientError> {
+    let n = |attribute| number(attributes, attribute);
+    Ok(match name {
+        "rect" => SerializableShape::Rectangle(SerializableRectangle {
+            x: n("x")?,
+            y: n("y")?,
+            width: n("width")?,
+            height: n("height")?,
+            color: color(attributes, "stroke")?,
+        }),
+        "circle" if find(attributes, "class") == Some(POINT_CLASS) => {
+            SerializableShape::Point(SerializablePoint {
+                x: n("cx")?,
+                y: n("cy")?,
+                color: color(attributes, "fill")?,
+            })
+        }
+        "circle" => SerializableShape::Circle(SerializableCircle {
+            x: n("cx")?,
+            y: n("cy")?,
+            radius: n("r")?,
+            color: color(attributes, "stroke")?,
+        }),
+        _ => SerializableShape::Line(SerializableLine {
+            x1: n("x1")?,
+            y1: n("y1")?,
+            x2: n("x2")?,
+            y2: n("y2")?,
+            color: color(attributes, "stroke")?,
+        }),
+    })
+}
+
+//the colour in the given paint attribute, the other one if that is unset, else black
+fn color(attributes: &[(String, String)], paint: &str) -> Result<SerializableColor, ClientError> {
+    let other = if paint == "stroke" { "fill" } else { "stroke" };
+    let value = [paint, other]
+        .iter()
+        .filter_map(|p| find(attributes, p))
+        .map(str::trim)
+        .find(|v| *v != "none");
+    match value {
+        None => Ok(SerializableColor::new(0, 0, 0)),
+        Some(value) => parse_color(value)
+            .ok_or_else(|| InvalidDrawing(format!("unsupported colour \"{}\"", value))),
+    }
+}
+
+//"#rrggbb", "#rgb" or "rgb(r, g, b)"
+fn parse_color(value: &str) -> Option<SerializableColor> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+        return match hex.len() {
+            6 => {
+                let byte = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+                Some(SerializableColor::new(byte(0)?, byte(2)?, byte(4)?))
+            }
+            3 => Some(SerializableColor::new(
+                digit(0)? * 17,
+                digit(1)? * 17,
+                digit(2)? * 17,
+            )),
+            _ => None,
+        };
+    }
+    let channels = value.strip_prefix("rgb(")?.strip_suffix(')')?;
+    let mut channels = channels.split(',').map(|c| c.trim().parse::<u8>().ok());
+    let color = SerializableColor::new(channels.next()??, channels.next()??, channels.next()??);
+    match channels.next() {
+        None => Some(color),
+        Some(_) => None,
+    }
+}
@@ -1,28 +1,256 @@
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::Path;
 use std::process::{Command, Stdio};
 
-#[cfg(target_os = "linux")]
-pub fn open_terminal_with_command(command: &str) -> std::io::Result<()> {
-    Command::new("terminator")
-        .arg("-e")
-        .arg(command)
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    Ok(())
-}
-
-#[cfg(target_os = "macos")]
-pub fn open_terminal_with_command(command: &str) -> std::io::Result<()> {
-    // Use AppleScript to open a new Terminal window and run a command
-    let applescript = format!(
-        r#"tell application "Terminal"
-    do script "{}"
-end tell"#,
-        command
-    );
-
-    Command::new("osascript")
-        .args(&["-e", &applescript])
-        .spawn()?;
-    Ok(())
+//names the terminal to use and its arguments, e.g. "alacritty -e", or "print" to never open one
+pub const TERMINAL_ENV: &str = "PICTORUST_TERMINAL";
+
+//a terminal emulator able to run a command in a new window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminal {
+    //runs the command given as the arguments following `args`
+    Emulator { program: String, args: Vec<String> },
+    //Terminal.app, driven through AppleScript
+    AppleTerminal,
+}
+
+impl Terminal {
+    pub fn emulator(program: &str, args: &[&str]) -> Self {
+        Terminal::Emulator {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        }
+    }
+
+    //"<program> <args>...", as written in the env var or a config entry
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut words = s.split_whitespace();
+        let program = words.next()?;
+        if program == "Terminal.app" {
+            return Some(Terminal::AppleTerminal);
+        }
+        Some(Terminal::Emulator {
+            program: program.to_string(),
+            args: words.map(|w| w.to_string()).collect(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Terminal::Emulator { program, .. } => program,
+            Terminal::AppleTerminal => "Terminal.app",
+        }
+    }
+
+    //the program to spawn and its arguments to run the command
+    pub fn invocation(&self, command: &[String]) -> (String, Vec<String>) {
+        match self {
+            Terminal::Emulator { program, args } => {
+                let mut args = args.clone();
+                args.extend(command.iter().cloned());
+                (program.clone(), args)
+            }
+            Terminal::AppleTerminal => ("osascript".to_string(), vec!["-".to_string()]),
+        }
+    }
+
+    fn program(&self) -> &str {
+        match self {
+            Terminal::Emulator { program, .. } => program,
+            Terminal::AppleTerminal => "osascript",
+        }
+    }
+
+    fn spawn(&self, command: &[String], env: &[(&str, &str)]) -> io::Result<()> {
+        let (program, args) = self.invocation(command);
+        let mut process = Command::new(program);
+        process
+            .args(args)
+            .envs(env.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        match self {
+            Terminal::Emulator { .. } => {
+                process.spawn()?;
+            }
+            Terminal::AppleTerminal => {
+                use std::io::Write;
+
+                //Terminal.app does not pass our environment on, so it goes in the script,
+                //which is written to stdin to stay out of the process list
+                let script = format!(
+                    "tell application \"Terminal\"\n    do script \"{}\"\nend tell",
+                    attach_line(command, env)
+                        .replace('\\', "\\\\")
+                        .replace('"', "\\\"")
+                );
+                let mut osascript = process.stdin(Stdio::piped()).spawn()?;
+                if let Some(mut stdin) = osascript.stdin.take() {
+                    stdin.write_all(script.as_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+//the emulators tried, in order, when none is configured
+pub fn known_terminals() -> Vec<Terminal> {
+    let mut terminals = Vec::new();
+    if cfg!(target_os = "macos") {
+        terminals.push(Terminal::AppleTerminal);
+    }
+    terminals.extend([
+        Terminal::emulator("terminator", &["-x"]),
+        Terminal::emulator("xterm", &["-e"]),
+        Terminal::emulator("gnome-terminal", &["--"]),
+        Terminal::emulator("konsole", &["-e"]),
+        Terminal::emulator("alacritty", &["-e"]),
+        Terminal::emulator("kitty", &[]),
+        Terminal::emulator("foot", &[]),
+        Terminal::emulator("wezterm", &["start", "--"]),
+    ]);
+    terminals
+}
+
+//where the command ends up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LaunchChoice {
+    Terminal(Terminal),
+    //no usable terminal, the caller shows the attach line for the user to run;
+    //it holds the env as well, secrets included
+    Print(String),
+}
+
+impl Display for LaunchChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchChoice::Terminal(terminal) => write!(f, "{}", terminal.name()),
+            LaunchChoice::Print(_) => write!(f, "attach command for the user to run"),
+        }
+    }
+}
+
+//picks a terminal: the env var, then the configured one, then the first known one installed
+pub struct Launcher {
+    env_override: Option<String>,
+    configured: Option<Terminal>,
+    candidates: Vec<Terminal>,
+    //whether a program can be run, swapped in tests
+    is_installed: fn(&str) -> bool,
+}
+
+impl Launcher {
+    pub fn new() -> Self {
+        Launcher {
+            env_override: env::var(TERMINAL_ENV).ok(),
+            configured: None,
+            candidates: known_terminals(),
+            is_installed: in_path,
+        }
+    }
+
+    //a terminal from the embedder's configuration, the env var still wins
+    pub fn with_terminal(mut self, terminal: Terminal) -> Self {
+        self.configured = Some(terminal);
+        self
+    }
+
+    pub fn with_env_override(mut self, env_override: Option<String>) -> Self {
+        self.env_override = env_override;
+        self
+    }
+
+    pub fn with_candidates(mut self, candidates: Vec<Terminal>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    pub fn with_lookup(mut self, is_installed: fn(&str) -> bool) -> Self {
+        self.is_installed = is_installed;
+        self
+    }
+
+    //the terminal to use, None if the command must be run by the user
+    pub fn select(&self) -> Option<Terminal> {
+        if let Some(env_override) = &self.env_override {
+            if env_override.trim() == "print" {
+                return None;
+            }
+            //asked for explicitly, so used even if it can't be found in PATH
+            if let Some(terminal) = Terminal::parse(env_override) {
+                return Some(terminal);
+            }
+        }
+        if let Some(terminal) = &self.configured {
+            return Some(terminal.clone());
+        }
+        self.candidates
+            .iter()
+            .find(|t| (self.is_installed)(t.program()))
+            .cloned()
+    }
+
+    //runs the command in the selected terminal, or hands back its attach line if that fails;
+    //env reaches the command without showing up in its arguments
+    pub fn launch(&self, command: &[String], env: &[(&str, &str)]) -> LaunchChoice {
+        if let Some(terminal) = self.select() {
+            if terminal.spawn(command, env).is_ok() {
+                return LaunchChoice::Terminal(terminal);
+            }
+        }
+        LaunchChoice::Print(attach_line(command, env))
+    }
+}
+
+impl Default for Launcher {
+    fn default() -> Self {
+        Launcher::new()
+    }
+}
+
+//the command as a line for a posix shell, env assignments first
+pub fn attach_line(command: &[String], env: &[(&str, &str)]) -> String {
+    env.iter()
+        .map(|(key, value)| format!("{}={}", key, shell_quote(value)))
+        .chain(command.iter().map(|arg| shell_quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@+,".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+//whether the program is an executable file in PATH, or at the given path
+pub fn in_path(program: &str) -> bool {
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| is_executable(&dir.join(program))))
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file() || path.with_extension("exe").is_file()
 }
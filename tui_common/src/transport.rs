@@ -0,0 +1,410 @@
+use crate::ClientError;
+use crate::ClientError::InvalidEndpoint;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+//a byte stream between the TUI and the backend
+pub trait Transport: Read + Write + Send {
+    //another handle to the same stream, e.g. one for reading and one for writing
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Transport>>;
+    //closes both directions for every handle, the other side sees the stream end
+    fn close(&self);
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+//makes a blocked accept_transport return, called from another thread
+pub type AcceptWaker = Box<dyn Fn() + Send>;
+
+//waits for the other side of the link to connect
+pub trait Listener: Send {
+    fn accept_transport(&self) -> io::Result<Box<dyn Transport>>;
+    //where the other side connects to, none if it can't be reached by address
+    fn endpoint(&self) -> Option<Endpoint>;
+    //wakes the listener up with a connection of its own, e.g. to have it see it must stop;
+    //by default the connection is made to the endpoint
+    fn waker(&self) -> AcceptWaker {
+        let endpoint = self.endpoint();
+        Box::new(move || {
+            if let Some(endpoint) = &endpoint {
+                drop(endpoint.connect());
+            }
+        })
+    }
+}
+
+impl Transport for TcpStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl Listener for TcpListener {
+    fn accept_transport(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = self.accept()?;
+        Ok(Box::new(stream))
+    }
+
+    fn endpoint(&self) -> Option<Endpoint> {
+        self.local_addr().ok().map(Endpoint::Tcp)
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn close(&self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+//a unix socket only its owner can connect to, removed when dropped
+#[cfg(unix)]
+pub struct UnixSocketListener {
+    listener: UnixListener,
+    path: PathBuf,
+    //the private directory created around the socket, if any
+    dir: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+impl UnixSocketListener {
+    //binds the socket at the given path, readable and writable by the owner only
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        use std::fs::{set_permissions, Permissions};
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)?;
+        set_permissions(&path, Permissions::from_mode(0o600))?;
+        Ok(UnixSocketListener {
+            listener,
+            path,
+            dir: None,
+        })
+    }
+
+    //binds the socket in a new directory only the owner can enter,
+    //so nobody can connect in the moment before the socket permissions are set
+    pub fn bind_private() -> io::Result<Self> {
+        use std::fs::DirBuilder;
+        use std::os::unix::fs::DirBuilderExt;
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        static NEXT_DIR: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "pictorust-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        DirBuilder::new().mode(0o700).create(&dir)?;
+        match UnixSocketListener::bind(dir.join("tui.sock")) {
+            Ok(mut listener) => {
+                listener.dir = Some(dir);
+                Ok(listener)
+            }
+            Err(e) => {
+                let _ = std::fs::remove_dir(&dir);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(unix)]
+impl Listener for UnixSocketListener {
+    fn accept_transport(&self) -> io::Result<Box<dyn Transport>> {
+        let (stream, _) = self.listener.accept()?;
+        Ok(Box::new(stream))
+    }
+
+    fn endpoint(&self) -> Option<Endpoint> {
+        Some(Endpoint::Unix(self.path.clone()))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketListener {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        if let Some(dir) = &self.dir {
+            let _ = std::fs::remove_dir(dir);
+        }
+    }
+}
+
+//one direction of an in-memory stream
+#[derive(Default)]
+struct Pipe {
+    //(bytes not read yet, closed)
+    buffer: Mutex<(VecDeque<u8>, bool)>,
+    readable: Condvar,
+}
+
+impl Pipe {
+    fn write(&self, bytes: &[u8]) -> io::Result<usize> {
+        let mut buffer = self.buffer.lock().map_err(|_| ErrorKind::Other)?;
+        if buffer.1 {
+            return Err(ErrorKind::BrokenPipe.into());
+        }
+        buffer.0.extend(bytes);
+        self.readable.notify_all();
+        Ok(bytes.len())
+    }
+
+    fn read(&self, bytes: &mut [u8], timeout: Option<Duration>) -> io::Result<usize> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut buffer = self.buffer.lock().map_err(|_| ErrorKind::Other)?;
+        loop {
+            if !buffer.0.is_empty() {
+                let n = bytes.len().min(buffer.0.len());
+                for (byte, read) in bytes.iter_mut().zip(buffer.0.drain(..n)) {
+                    *byte = read;
+                }
+                return Ok(n);
+            }
+            if buffer.1 {
+                return Ok(0);
+            }
+            buffer = match deadline {
+                None => self.readable.wait(buffer).map_err(|_| ErrorKind::Other)?,
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(ErrorKind::WouldBlock.into());
+                    }
+                    self.readable
+                        .wait_timeout(buffer, deadline - now)
+                        .map_err(|_| ErrorKind::Other)?
+                        .0
+                }
+            };
+        }
+    }
+
+    fn close(&self) {
+        if let Ok(mut buffer) = self.buffer.lock() {
+            buffer.1 = true;
+        }
+        self.readable.notify_all();
+    }
+}
+
+//closes the pipes once the last handle to this end is dropped
+struct MemoryEnd {
+    incoming: Arc<Pipe>,
+    outgoing: Arc<Pipe>,
+}
+
+impl Drop for MemoryEnd {
+    fn drop(&mut self) {
+        self.incoming.close();
+        self.outgoing.close();
+    }
+}
+
+//one end of an in-process stream, for tests and for TUI and backend in the same process
+#[derive(Clone)]
+pub struct MemoryStream {
+    end: Arc<MemoryEnd>,
+    //shared by the clones, like a socket option
+    read_timeout: Arc<Mutex<Option<Duration>>>,
+}
+
+//two connected in-memory streams
+pub fn memory_pair() -> (MemoryStream, MemoryStream) {
+    let a_to_b = Arc::new(Pipe::default());
+    let b_to_a = Arc::new(Pipe::default());
+    let a = MemoryStream {
+        end: Arc::new(MemoryEnd {
+            incoming: Arc::clone(&b_to_a),
+            outgoing: Arc::clone(&a_to_b),
+        }),
+        read_timeout: Arc::new(Mutex::new(None)),
+    };
+    let b = MemoryStream {
+        end: Arc::new(MemoryEnd {
+            incoming: a_to_b,
+            outgoing: b_to_a,
+        }),
+        read_timeout: Arc::new(Mutex::new(None)),
+    };
+    (a, b)
+}
+
+impl Read for MemoryStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let timeout = *self.read_timeout.lock().map_err(|_| ErrorKind::Other)?;
+        self.end.incoming.read(buf, timeout)
+    }
+}
+
+impl Write for MemoryStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.end.outgoing.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for MemoryStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn close(&self) {
+        self.end.incoming.close();
+        self.end.outgoing.close();
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.read_timeout.lock().map_err(|_| ErrorKind::Other)? = timeout;
+        Ok(())
+    }
+}
+
+//accepts the in-memory streams opened through its connectors
+pub struct MemoryListener {
+    incoming: Receiver<MemoryStream>,
+    //reaches the connectors' side without keeping it open
+    outgoing: Weak<Sender<MemoryStream>>,
+}
+
+//opens in-memory streams to a listener, can be cloned and moved to other threads
+#[derive(Clone)]
+pub struct MemoryConnector {
+    outgoing: Arc<Sender<MemoryStream>>,
+}
+
+pub fn memory_listener() -> (MemoryListener, MemoryConnector) {
+    let (outgoing, incoming) = channel();
+    let outgoing = Arc::new(outgoing);
+    let listener = MemoryListener {
+        incoming,
+        outgoing: Arc::downgrade(&outgoing),
+    };
+    (listener, MemoryConnector { outgoing })
+}
+
+impl MemoryConnector {
+    pub fn connect(&self) -> io::Result<MemoryStream> {
+        let (ours, theirs) = memory_pair();
+        self.outgoing
+            .send(theirs)
+            .map_err(|_| io::Error::from(ErrorKind::ConnectionRefused))?;
+        Ok(ours)
+    }
+}
+
+impl Listener for MemoryListener {
+    fn accept_transport(&self) -> io::Result<Box<dyn Transport>> {
+        match self.incoming.recv() {
+            Ok(stream) => Ok(Box::new(stream)),
+            //every connector is gone, nobody can connect anymore
+            Err(_) => Err(ErrorKind::ConnectionAborted.into()),
+        }
+    }
+
+    fn endpoint(&self) -> Option<Endpoint> {
+        None
+    }
+
+    //connects like a connector would, while there is still one
+    fn waker(&self) -> AcceptWaker {
+        let outgoing = self.outgoing.clone();
+        Box::new(move || {
+            if let Some(outgoing) = outgoing.upgrade() {
+                let (_ours, theirs) = memory_pair();
+                let _ = outgoing.send(theirs);
+            }
+        })
+    }
+}
+
+//an address the TUI can be told to connect to, written as "tcp:<address>" or "unix:<path>"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    pub fn connect(&self) -> io::Result<Box<dyn Transport>> {
+        match self {
+            Endpoint::Tcp(address) => Ok(Box::new(TcpStream::connect(address)?)),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => Ok(Box::new(UnixStream::connect(path)?)),
+        }
+    }
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Tcp(address) => write!(f, "tcp:{}", address),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = ClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidEndpoint(s.to_string());
+        if let Some(address) = s.strip_prefix("tcp:") {
+            return address.parse().map(Endpoint::Tcp).map_err(|_| invalid());
+        }
+        if let Some(path) = s.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return match path {
+                "" => Err(invalid()),
+                path => Ok(Endpoint::Unix(PathBuf::from(path))),
+            };
+            #[cfg(not(unix))]
+            return Err(InvalidEndpoint(format!(
+                "{} (unix sockets are not supported on this platform)",
+                path
+            )));
+        }
+        //a bare port, as older backends pass it
+        if let Ok(port) = s.parse::<u16>() {
+            return Ok(Endpoint::Tcp(SocketAddr::from(([127, 0, 0, 1], port))));
+        }
+        s.parse().map(Endpoint::Tcp).map_err(|_| invalid())
+    }
+}
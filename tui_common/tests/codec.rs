@@ -0,0 +1,152 @@
+use client_lib::codec::{BinaryCodec, Codec, JsonCodec, WireCodec};
+use client_lib::communication::MessageContent::{Drawing, TextMessage};
+use client_lib::communication::{
+    now, MessageContent, MessageStatus, Reaction, TUICommand, TUIEvent,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+fn drawing() -> MessageContent {
+    let drawing = serde_json::from_str(
+        r#"{"shapes":[
+            {"Rectangle":{"x":1.0,"y":2.0,"width":3.5,"height":4.25,"color":{"r":255,"g":0,"b":0}}},
+            {"Circle":{"x":-1.0,"y":0.5,"radius":2.0,"color":{"r":0,"g":255,"b":0}}},
+            {"Line":{"x1":0.0,"y1":0.0,"x2":10.0,"y2":-10.0,"color":{"r":0,"g":0,"b":255}}},
+            {"Point":{"x":0.1,"y":0.2,"color":{"r":1,"g":2,"b":3}}}
+        ]}"#,
+    )
+    .unwrap();
+    Drawing(drawing)
+}
+
+fn events() -> Vec<TUIEvent> {
+    vec![
+        TUIEvent::SendMessage(1, 2, 3, TextMessage("hello ✓".to_string())),
+        TUIEvent::SendMessage(1, 2, u64::MAX, drawing()),
+        TUIEvent::EditMessage(1, 2, 3, TextMessage("hello again".to_string())),
+        TUIEvent::ReadMessage(1, 2, 3),
+        TUIEvent::DeleteMessage(1, 2, 3),
+        TUIEvent::ReactToMessage(1, 2, 3, Some(Reaction::Skull)),
+        TUIEvent::ReactToMessage(1, 2, 3, None),
+        TUIEvent::SetName("name with spaces".to_string()),
+        TUIEvent::RegisterToServer(255),
+        TUIEvent::RequestRoomList(0),
+        TUIEvent::Dead,
+    ]
+}
+
+fn commands() -> Vec<TUICommand> {
+    vec![
+        TUICommand::UpdateName("".to_string()),
+        TUICommand::UpdateChatRoom(1, Some(true), None),
+        TUICommand::UpdatePeerName(1, 2, Some("peer".to_string())),
+        TUICommand::UpdatePeerName(1, 2, None),
+        TUICommand::UpdatePeerLastSeen(1, 2, u64::MAX),
+        TUICommand::UpdatePeerStatus(1, 2, false),
+        TUICommand::UpdateMessageContent(1, 2, 3, TextMessage("hi".to_string()), 0),
+        TUICommand::UpdateMessageContent(1, 2, 3, drawing(), 1_700_000_000),
+        TUICommand::UpdateMessageStatus(1, 2, 3, MessageStatus::FailedToSend),
+        TUICommand::UpdateMessageReaction(1, 2, 3, Some(Reaction::Heart)),
+        TUICommand::UpdateMessageReaction(1, 2, 3, None),
+        TUICommand::DeleteMessage(1, 2, 3),
+        TUICommand::Kill,
+    ]
+}
+
+//fails to compile when a variant is added, so the lists above must follow
+fn event_name(event: &TUIEvent) -> &'static str {
+    match event {
+        TUIEvent::SendMessage(..) => "SendMessage",
+        TUIEvent::EditMessage(..) => "EditMessage",
+        TUIEvent::ReadMessage(..) => "ReadMessage",
+        TUIEvent::DeleteMessage(..) => "DeleteMessage",
+        TUIEvent::ReactToMessage(..) => "ReactToMessage",
+        TUIEvent::SetName(..) => "SetName",
+        TUIEvent::RegisterToServer(..) => "RegisterToServer",
+        TUIEvent::RequestRoomList(..) => "RequestRoomList",
+        TUIEvent::Dead => "Dead",
+    }
+}
+
+fn command_name(command: &TUICommand) -> &'static str {
+    match command {
+        TUICommand::UpdateName(..) => "UpdateName",
+        TUICommand::UpdateChatRoom(..) => "UpdateChatRoom",
+        TUICommand::UpdatePeerName(..) => "UpdatePeerName",
+        TUICommand::UpdatePeerLastSeen(..) => "UpdatePeerLastSeen",
+        TUICommand::UpdatePeerStatus(..) => "UpdatePeerStatus",
+        TUICommand::UpdateMessageContent(..) => "UpdateMessageContent",
+        TUICommand::UpdateMessageStatus(..) => "UpdateMessageStatus",
+        TUICommand::UpdateMessageReaction(..) => "UpdateMessageReaction",
+        TUICommand::DeleteMessage(..) => "DeleteMessage",
+        TUICommand::Kill => "Kill",
+    }
+}
+
+fn assert_round_trip<C: Codec, T: Serialize + DeserializeOwned + PartialEq + Debug>(
+    codec: &C,
+    value: &T,
+) {
+    let bytes = codec.encode(value).unwrap();
+    let decoded: T = codec.decode(&bytes).unwrap();
+    assert_eq!(&decoded, value);
+}
+
+fn assert_all_round_trip<C: Codec>(codec: &C) {
+    for event in events() {
+        assert_round_trip(codec, &event);
+    }
+    for command in commands() {
+        assert_round_trip(codec, &command);
+    }
+}
+
+#[test]
+fn every_variant_is_listed() {
+    let mut event_names = events().iter().map(event_name).collect::<Vec<_>>();
+    event_names.dedup();
+    assert_eq!(event_names.len(), 9);
+    let mut command_names = commands().iter().map(command_name).collect::<Vec<_>>();
+    command_names.dedup();
+    assert_eq!(command_names.len(), 10);
+}
+
+#[test]
+fn json_round_trips() {
+    assert_all_round_trip(&JsonCodec);
+    assert_all_round_trip(&WireCodec::Json);
+}
+
+#[test]
+fn binary_round_trips() {
+    assert_all_round_trip(&BinaryCodec);
+    assert_all_round_trip(&WireCodec::Binary);
+}
+
+#[test]
+fn binary_drawings_are_smaller() {
+    let command = TUICommand::UpdateMessageContent(1, 2, 3, drawing(), 0);
+    let json = JsonCodec.encode(&command).unwrap();
+    let binary = BinaryCodec.encode(&command).unwrap();
+    assert!(binary.len() < json.len());
+}
+
+#[test]
+fn garbage_is_an_error() {
+    let garbage = [0xff, 0xff, 0xff, 0xff, 0x01];
+    assert!(JsonCodec.decode::<TUICommand>(&garbage).is_err());
+    assert!(BinaryCodec.decode::<TUICommand>(&garbage).is_err());
+}
+
+#[test]
+fn json_content_from_builds_without_the_time_is_read() {
+    let before = now();
+    let older = br#"{"UpdateMessageContent":[1,2,3,{"TextMessage":"hi"}]}"#;
+    let command = JsonCodec.decode::<TUICommand>(older).unwrap();
+    let TUICommand::UpdateMessageContent(1, 2, 3, TextMessage(text), time) = command else {
+        panic!("unexpected command {:?}", command);
+    };
+    assert_eq!(text, "hi");
+    assert!(time >= before);
+}
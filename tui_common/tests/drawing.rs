@@ -0,0 +1,57 @@
+use client_lib::communication::{
+    Drawing, SerializableCircle, SerializableColor, SerializableLine, SerializablePoint,
+    SerializableRectangle, SerializableShape,
+};
+
+const BLACK: SerializableColor = SerializableColor::new(0, 0, 0);
+
+fn point(x: f64, y: f64) -> SerializableShape {
+    SerializableShape::Point(SerializablePoint { x, y, color: BLACK })
+}
+
+#[test]
+fn bounds_cover_every_shape() {
+    let d = Drawing::new()
+        .with_shape(SerializableShape::Rectangle(SerializableRectangle {
+            x: 10.0,
+            y: 5.0,
+            width: -4.0,
+            height: 2.0,
+            color: BLACK,
+        }))
+        .with_shape(SerializableShape::Circle(SerializableCircle {
+            x: 0.0,
+            y: 0.0,
+            radius: 3.0,
+            color: SerializableColor::new(255, 0, 0),
+        }))
+        .with_shape(SerializableShape::Line(SerializableLine {
+            x1: 1.0,
+            y1: 20.0,
+            x2: -1.0,
+            y2: 4.0,
+            color: SerializableColor::new(0, 255, 0),
+        }))
+        .with_shape(point(12.0, 1.0));
+    assert_eq!(d.shapes().len(), 4);
+    assert_eq!(d.bounds(), Some((-3.0, -3.0, 12.0, 20.0)));
+    assert_eq!(Drawing::new().bounds(), None);
+}
+
+#[test]
+fn shapes_can_be_undone_and_cleared() {
+    let mut d = Drawing::from(vec![point(1.0, 1.0)]);
+    d.push(point(2.0, 2.0));
+    assert_eq!(d.pop(), Some(point(2.0, 2.0)));
+    assert_eq!(d.shapes(), [point(1.0, 1.0)]);
+    d.clear();
+    assert!(d.is_empty());
+    assert_eq!(d.pop(), None);
+}
+
+#[test]
+fn built_drawings_survive_the_wire() {
+    let d = Drawing::from(vec![point(1.5, -2.0), point(3.0, 4.0)]);
+    let json = serde_json::to_string(&d).unwrap();
+    assert_eq!(serde_json::from_str::<Drawing>(&json).unwrap(), d);
+}
@@ -0,0 +1,243 @@
+use client_lib::communication::{
+    Drawing, SerializableCircle, SerializableColor, SerializableLine, SerializablePoint,
+    SerializableRectangle, SerializableShape,
+};
+use client_lib::pnm::{luma, raster_size, to_pgm, to_ppm};
+use client_lib::svg::{from_svg, to_svg};
+use client_lib::ClientError;
+
+//how many random drawings each property is checked against
+const CASES: u64 = 300;
+
+//xorshift, enough to spread the cases and the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    //any finite coordinate, fractions included
+    fn coordinate(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64 * 2000.0 - 1000.0
+    }
+
+    fn length(&mut self) -> f64 {
+        self.coordinate().abs()
+    }
+
+    fn color(&mut self) -> SerializableColor {
+        let [r, g, b, ..] = self.next().to_le_bytes();
+        SerializableColor::new(r, g, b)
+    }
+
+    fn shape(&mut self) -> SerializableShape {
+        match self.below(4) {
+            0 => SerializableShape::Rectangle(SerializableRectangle {
+                x: self.coordinate(),
+                y: self.coordinate(),
+                width: self.length(),
+                height: self.length(),
+                color: self.color(),
+            }),
+            1 => SerializableShape::Circle(SerializableCircle {
+                x: self.coordinate(),
+                y: self.coordinate(),
+                radius: self.length(),
+                color: self.color(),
+            }),
+            2 => SerializableShape::Line(SerializableLine {
+                x1: self.coordinate(),
+                y1: self.coordinate(),
+                x2: self.coordinate(),
+                y2: self.coordinate(),
+                color: self.color(),
+            }),
+            _ => SerializableShape::Point(SerializablePoint {
+                x: self.coordinate(),
+                y: self.coordinate(),
+                color: self.color(),
+            }),
+        }
+    }
+
+    fn drawing(&mut self) -> Drawing {
+        let shapes = self.below(12);
+        (0..shapes).map(|_| self.shape()).collect::<Vec<_>>().into()
+    }
+}
+
+fn cases() -> impl Iterator<Item = (u64, Drawing)> {
+    (0..CASES).map(|seed| (seed, Rng::new(seed).drawing()))
+}
+
+//(width, height, pixels) of a binary pnm with the given magic number
+fn parse_pnm<'a>(magic: &str, image: &'a [u8]) -> (u32, u32, &'a [u8]) {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    while fields.len() < 4 {
+        let end = start
+            + image[start..]
+                .iter()
+                .position(|b| *b == b'\n' || *b == b' ')
+                .unwrap();
+        fields.push(std::str::from_utf8(&image[start..end]).unwrap());
+        start = end + 1;
+    }
+    assert_eq!(fields[0], magic);
+    assert_eq!(fields[3], "255");
+    (
+        fields[1].parse().unwrap(),
+        fields[2].parse().unwrap(),
+        &image[start..],
+    )
+}
+
+#[test]
+fn svg_round_trips() {
+    for (seed, drawing) in cases() {
+        let svg = to_svg(&drawing);
+        let back = from_svg(&svg).unwrap_or_else(|e| panic!("case {}: {}\n{}", seed, e, svg));
+        assert_eq!(back, drawing, "case {}:\n{}", seed, svg);
+        assert_eq!(to_svg(&back), svg, "case {}", seed);
+    }
+}
+
+#[test]
+fn rasters_have_the_requested_size_and_agree() {
+    for (seed, drawing) in cases() {
+        let mut rng = Rng::new(seed);
+        let width = 1 + rng.below(120) as u32;
+        let (width, height) = raster_size(&drawing, width);
+        let ppm = to_ppm(&drawing, width, height);
+        let pgm = to_pgm(&drawing, width, height);
+        let (w, h, rgb) = parse_pnm("P6", &ppm);
+        assert_eq!((w, h), (width, height), "case {}", seed);
+        assert_eq!(rgb.len(), (width * height * 3) as usize, "case {}", seed);
+        let (_, _, grey) = parse_pnm("P5", &pgm);
+        let expected = rgb
+            .chunks(3)
+            .map(|p| luma(&[p[0], p[1], p[2]]))
+            .collect::<Vec<_>>();
+        assert_eq!(grey, expected, "case {}", seed);
+    }
+}
+
+#[test]
+fn every_shape_leaves_its_colour_in_the_raster() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let shape = rng.shape();
+        let color = shape.color().clone();
+        //white is the background
+        if color == SerializableColor::new(255, 255, 255) {
+            continue;
+        }
+        let drawing = Drawing::new().with_shape(shape);
+        let (width, height) = raster_size(&drawing, 64);
+        let ppm = to_ppm(&drawing, width, height);
+        let (_, _, rgb) = parse_pnm("P6", &ppm);
+        assert!(
+            rgb.chunks(3).any(|p| p == [color.r, color.g, color.b]),
+            "case {}: {:?}",
+            seed,
+            drawing
+        );
+    }
+}
+
+#[test]
+fn negative_rectangles_are_exported_from_their_top_left_corner() {
+    let black = SerializableColor::new(0, 0, 0);
+    let drawing = Drawing::new().with_shape(SerializableShape::Rectangle(SerializableRectangle {
+        x: 10.0,
+        y: 10.0,
+        width: -4.0,
+        height: -2.0,
+        color: black.clone(),
+    }));
+    let back = from_svg(&to_svg(&drawing)).unwrap();
+    assert_eq!(
+        back.shapes(),
+        [SerializableShape::Rectangle(SerializableRectangle {
+            x: 6.0,
+            y: 8.0,
+            width: 4.0,
+            height: 2.0,
+            color: black,
+        })]
+    );
+    assert_eq!(back.bounds(), drawing.bounds());
+}
+
+#[test]
+fn svg_from_other_editors_is_read() {
+    let svg = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!-- made elsewhere -->
+<svg xmlns="http://www.w3.org/2000/svg" width="100px" height="50px">
+  <title>a house</title>
+  <sodipodi:namedview pagecolor="#ffffff"/>
+  <g id="layer1">
+    <rect x="10px" y="20" width="30" height="25" style="fill:none;stroke:#0f0"/>
+    <line x1='10' y1='20' x2='25' y2='5' stroke="rgb(255, 0, 0)"></line>
+    <circle cx="25" cy="12" r="3" fill="#0000ff"/>
+  </g>
+</svg>"##;
+    let drawing = from_svg(svg).unwrap();
+    assert_eq!(
+        drawing.shapes(),
+        [
+            SerializableShape::Rectangle(SerializableRectangle {
+                x: 10.0,
+                y: 20.0,
+                width: 30.0,
+                height: 25.0,
+                color: SerializableColor::new(0, 255, 0),
+            }),
+            SerializableShape::Line(SerializableLine {
+                x1: 10.0,
+                y1: 20.0,
+                x2: 25.0,
+                y2: 5.0,
+                color: SerializableColor::new(255, 0, 0),
+            }),
+            //no stroke, so the fill is used
+            SerializableShape::Circle(SerializableCircle {
+                x: 25.0,
+                y: 12.0,
+                radius: 3.0,
+                color: SerializableColor::new(0, 0, 255),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn svg_outside_the_subset_is_refused() {
+    for svg in [
+        "",
+        "<rect width=\"1\" height=\"1\"/>",
+        "<svg><path d=\"M 0 0 L 1 1\"/></svg>",
+        "<svg><rect x=\"1\" transform=\"rotate(45)\"/></svg>",
+        "<svg><rect x=\"one\"/></svg>",
+        "<svg><line stroke=\"papayawhip\"/></svg>",
+        "<svg><circle r=\"1\"",
+    ] {
+        assert!(
+            matches!(from_svg(svg), Err(ClientError::InvalidDrawing(_))),
+            "{:?} was accepted",
+            svg
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use client_lib::codec::JsonCodec;
+use client_lib::communication::{
+    receive_message, receive_message_with, send_message, send_message_with, TUICommand,
+    MAX_FRAME_SIZE,
+};
+use client_lib::ClientError;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+
+//two ends of a local tcp connection
+fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let writer = TcpStream::connect(address).unwrap();
+    let (reader, _) = listener.accept().unwrap();
+    (writer, reader)
+}
+
+#[test]
+fn oversized_header_is_refused_before_reading_the_body() {
+    let (mut writer, mut reader) = pair();
+    writer.write_all(&u32::MAX.to_be_bytes()).unwrap();
+    let result = receive_message::<TUICommand>(&mut reader);
+    assert!(matches!(
+        result,
+        Err(ClientError::FrameTooLarge(size, MAX_FRAME_SIZE)) if size == u32::MAX as usize
+    ));
+}
+
+#[test]
+fn frame_over_a_custom_limit_is_not_sent() {
+    let (mut writer, _reader) = pair();
+    let result = send_message_with(
+        &mut writer,
+        &JsonCodec,
+        TUICommand::UpdateName("a long name".to_string()),
+        4,
+    );
+    assert!(matches!(result, Err(ClientError::FrameTooLarge(_, 4))));
+}
+
+#[test]
+fn clean_close_between_frames_is_connection_closed() {
+    let (mut writer, mut reader) = pair();
+    send_message(&mut writer, TUICommand::Kill).unwrap();
+    drop(writer);
+    assert_eq!(
+        receive_message::<TUICommand>(&mut reader).unwrap(),
+        TUICommand::Kill
+    );
+    assert!(matches!(
+        receive_message::<TUICommand>(&mut reader),
+        Err(ClientError::ConnectionClosed)
+    ));
+}
+
+#[test]
+fn close_in_the_middle_of_a_frame_is_an_io_error() {
+    let (mut writer, mut reader) = pair();
+    writer.write_all(&10u32.to_be_bytes()).unwrap();
+    writer.write_all(b"{\"Up").unwrap();
+    drop(writer);
+    assert!(matches!(
+        receive_message::<TUICommand>(&mut reader),
+        Err(ClientError::Io(_))
+    ));
+
+    let (mut writer, mut reader) = pair();
+    writer.write_all(&[0, 0]).unwrap();
+    drop(writer);
+    assert!(matches!(
+        receive_message::<TUICommand>(&mut reader),
+        Err(ClientError::Io(_))
+    ));
+}
+
+#[test]
+fn undecodable_frame_leaves_the_stream_usable() {
+    let (mut writer, mut reader) = pair();
+    send_message(&mut writer, "not a command").unwrap();
+    send_message(&mut writer, TUICommand::Kill).unwrap();
+    assert!(matches!(
+        receive_message_with::<_, TUICommand>(&mut reader, &JsonCodec, MAX_FRAME_SIZE),
+        Err(ClientError::Decode(_))
+    ));
+    assert_eq!(
+        receive_message::<TUICommand>(&mut reader).unwrap(),
+        TUICommand::Kill
+    );
+}
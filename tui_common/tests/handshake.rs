@@ -0,0 +1,173 @@
+use client_lib::auth::{new_token, Auth};
+use client_lib::codec::WireCodec;
+use client_lib::communication::{
+    handshake, receive_message, send_message, Capability, Connection, Hello, PROTOCOL_VERSION,
+};
+use client_lib::ClientError;
+use std::error::Error;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+//runs the handshake against a peer that answers with the given raw hello
+fn handshake_with(peer_hello: String) -> Result<Connection, ClientError> {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = receive_message::<Hello>(&mut stream);
+        let peer_hello: serde_json::Value = serde_json::from_str(&peer_hello).unwrap();
+        send_message(&mut stream, peer_hello).unwrap();
+    });
+    let stream = TcpStream::connect(address).unwrap();
+    let result = handshake(
+        Box::new(stream),
+        &[Capability::Drawings, Capability::BinaryCodec],
+        Auth::Open,
+    );
+    peer.join().unwrap();
+    result
+}
+
+//runs a handshake requiring the token against a peer speaking first,
+//returns what the peer got back
+fn require_token_from(peer_hello: String) -> (Result<Connection, ClientError>, Option<Hello>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let peer_hello: serde_json::Value = serde_json::from_str(&peer_hello).unwrap();
+        send_message(&mut stream, peer_hello).unwrap();
+        receive_message::<Hello>(&mut stream).ok()
+    });
+    let (stream, _) = listener.accept().unwrap();
+    let result = handshake(Box::new(stream), &[], Auth::Require("secret"));
+    (result, peer.join().unwrap())
+}
+
+#[test]
+fn only_shared_capabilities_are_kept() {
+    let peer_hello = format!(
+        r#"{{"protocol_version":{},"capabilities":["Drawings","Reactions","Teleport"]}}"#,
+        PROTOCOL_VERSION
+    );
+    let connection = handshake_with(peer_hello).unwrap();
+    assert_eq!(connection.capabilities(), &[Capability::Drawings]);
+    assert_eq!(connection.codec(), WireCodec::Json);
+}
+
+#[test]
+fn binary_codec_is_used_when_both_sides_support_it() {
+    let peer_hello = format!(
+        r#"{{"protocol_version":{},"capabilities":["BinaryCodec"]}}"#,
+        PROTOCOL_VERSION
+    );
+    let connection = handshake_with(peer_hello).unwrap();
+    assert_eq!(connection.codec(), WireCodec::Binary);
+}
+
+#[test]
+fn different_protocol_version_is_refused() {
+    let peer_hello = format!(
+        r#"{{"protocol_version":{},"capabilities":[]}}"#,
+        PROTOCOL_VERSION + 1
+    );
+    match handshake_with(peer_hello) {
+        Err(ClientError::VersionMismatch(ours, theirs)) => {
+            assert_eq!(ours, PROTOCOL_VERSION);
+            assert_eq!(theirs, PROTOCOL_VERSION + 1);
+        }
+        other => panic!("expected a version mismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn garbage_instead_of_hello_is_refused() {
+    let result = handshake_with(r#"{"SetName":"mallory"}"#.to_string());
+    let Err(ClientError::HandshakeError(cause)) = result else {
+        panic!("expected a handshake error, got {:?}", result);
+    };
+    assert!(matches!(*cause, ClientError::Decode(_)), "{:?}", cause);
+}
+
+#[test]
+fn oversized_hello_is_refused_with_its_size() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let _ = receive_message::<Hello>(&mut stream);
+        //only the header of a frame far bigger than any hello
+        stream.write_all(&(1u32 << 30).to_be_bytes()).unwrap();
+        stream
+    });
+    let stream = TcpStream::connect(address).unwrap();
+    let result = handshake(Box::new(stream), &[], Auth::Open);
+    let _peer = peer.join().unwrap();
+
+    let Err(error @ ClientError::HandshakeError(_)) = result else {
+        panic!("expected a handshake error, got {:?}", result);
+    };
+    let cause = error.source().unwrap().to_string();
+    assert!(cause.contains(&(1u32 << 30).to_string()), "{}", cause);
+    assert!(error.to_string().ends_with(&cause), "{}", error);
+}
+
+#[test]
+fn presented_token_is_sent_in_the_hello() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let peer = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let hello = receive_message::<Hello>(&mut stream).unwrap();
+        send_message(&mut stream, hello.clone()).unwrap();
+        hello
+    });
+    let stream = TcpStream::connect(address).unwrap();
+    handshake(Box::new(stream), &[], Auth::Present("secret")).unwrap();
+    assert_eq!(peer.join().unwrap().token.as_deref(), Some("secret"));
+}
+
+#[test]
+fn required_token_is_accepted() {
+    let (result, reply) = require_token_from(format!(
+        r#"{{"protocol_version":{},"capabilities":[],"token":"secret"}}"#,
+        PROTOCOL_VERSION
+    ));
+    assert!(result.is_ok());
+    let reply = reply.expect("the hello is sent once the token is checked");
+    assert_eq!(reply.token, None);
+}
+
+#[test]
+fn wrong_or_missing_token_is_refused_without_an_answer() {
+    let hellos = [
+        format!(
+            r#"{{"protocol_version":{},"capabilities":[],"token":"secreT"}}"#,
+            PROTOCOL_VERSION
+        ),
+        format!(
+            r#"{{"protocol_version":{},"capabilities":[],"token":"secret "}}"#,
+            PROTOCOL_VERSION
+        ),
+        //a build from before tokens existed
+        format!(
+            r#"{{"protocol_version":{},"capabilities":[]}}"#,
+            PROTOCOL_VERSION
+        ),
+    ];
+    for hello in hellos {
+        let (result, reply) = require_token_from(hello);
+        assert!(matches!(result, Err(ClientError::Unauthorized)));
+        assert_eq!(reply, None);
+    }
+}
+
+#[test]
+fn tokens_are_random() {
+    let a = new_token();
+    let b = new_token();
+    assert_eq!(a.len(), 64);
+    assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_ne!(a, b);
+}
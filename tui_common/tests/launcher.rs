@@ -0,0 +1,129 @@
+use client_lib::sys::{attach_line, known_terminals, LaunchChoice, Launcher, Terminal};
+
+fn nothing_installed(_: &str) -> bool {
+    false
+}
+
+fn only_kitty_and_foot(program: &str) -> bool {
+    program == "kitty" || program == "foot"
+}
+
+fn everything_installed(_: &str) -> bool {
+    true
+}
+
+//a launcher that never looks at the real environment
+fn launcher(is_installed: fn(&str) -> bool) -> Launcher {
+    Launcher::new()
+        .with_env_override(None)
+        .with_lookup(is_installed)
+}
+
+fn chosen(choice: Option<Terminal>) -> Terminal {
+    choice.expect("no terminal chosen")
+}
+
+#[test]
+fn first_installed_terminal_is_chosen() {
+    let terminal = chosen(launcher(only_kitty_and_foot).select());
+    assert_eq!(terminal, Terminal::emulator("kitty", &[]));
+}
+
+#[test]
+fn without_terminals_the_command_is_printed() {
+    assert_eq!(launcher(nothing_installed).select(), None);
+}
+
+#[test]
+fn configured_terminal_wins_over_probing() {
+    let configured = Terminal::emulator("my-term", &["--run"]);
+    let launcher = launcher(everything_installed).with_terminal(configured.clone());
+    assert_eq!(chosen(launcher.select()), configured);
+}
+
+#[test]
+fn env_var_wins_over_everything() {
+    let launcher = launcher(nothing_installed)
+        .with_terminal(Terminal::emulator("my-term", &[]))
+        .with_env_override(Some("wezterm start --".to_string()));
+    assert_eq!(
+        chosen(launcher.select()),
+        Terminal::emulator("wezterm", &["start", "--"])
+    );
+
+    let launcher = launcher.with_env_override(Some(" print ".to_string()));
+    assert_eq!(launcher.select(), None);
+
+    //an empty value is as good as none
+    let launcher = self::launcher(only_kitty_and_foot).with_env_override(Some("  ".to_string()));
+    assert_eq!(chosen(launcher.select()), Terminal::emulator("kitty", &[]));
+}
+
+#[test]
+fn candidates_can_be_replaced() {
+    let launcher =
+        launcher(everything_installed).with_candidates(vec![Terminal::emulator("foot", &[])]);
+    assert_eq!(chosen(launcher.select()), Terminal::emulator("foot", &[]));
+}
+
+#[test]
+fn every_requested_emulator_is_probed() {
+    let names = known_terminals()
+        .iter()
+        .map(|t| t.name().to_string())
+        .collect::<Vec<_>>();
+    for name in [
+        "xterm",
+        "gnome-terminal",
+        "konsole",
+        "alacritty",
+        "kitty",
+        "foot",
+        "wezterm",
+    ] {
+        assert!(names.iter().any(|n| n == name), "{} is not probed", name);
+    }
+}
+
+#[test]
+fn command_follows_the_terminal_arguments() {
+    let command = vec!["/opt/pictorust".to_string(), "chat-tui".to_string()];
+    let (program, args) = Terminal::emulator("gnome-terminal", &["--"]).invocation(&command);
+    assert_eq!(program, "gnome-terminal");
+    assert_eq!(args, ["--", "/opt/pictorust", "chat-tui"]);
+
+    assert_eq!(
+        Terminal::parse("alacritty -e"),
+        Some(Terminal::emulator("alacritty", &["-e"]))
+    );
+    assert_eq!(
+        Terminal::parse("Terminal.app"),
+        Some(Terminal::AppleTerminal)
+    );
+    assert_eq!(Terminal::parse(""), None);
+}
+
+#[test]
+fn attach_line_is_quoted_for_the_shell() {
+    let command = vec![
+        "/home/me/my games/pictorust".to_string(),
+        "chat-tui".to_string(),
+        "unix:/tmp/it's.sock".to_string(),
+    ];
+    assert_eq!(
+        attach_line(&command, &[("PICTORUST_TOKEN", "abc123")]),
+        r#"PICTORUST_TOKEN=abc123 '/home/me/my games/pictorust' chat-tui 'unix:/tmp/it'\''s.sock'"#
+    );
+}
+
+#[test]
+fn without_a_terminal_the_attach_line_is_handed_back() {
+    let command = vec!["/opt/pictorust".to_string(), "chat-tui".to_string()];
+    let env = [("PICTORUST_TOKEN", "abc123")];
+    let choice = launcher(everything_installed)
+        .with_env_override(Some("print".to_string()))
+        .launch(&command, &env);
+    assert_eq!(choice, LaunchChoice::Print(attach_line(&command, &env)));
+    //shown in logs, so it must not carry the token
+    assert!(!choice.to_string().contains("abc123"));
+}
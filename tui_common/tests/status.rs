@@ -0,0 +1,53 @@
+use client_lib::communication::MessageStatus::{
+    self, FailedToSend, MessageFromPeer, ReadByPeer, ReceivedByPeer, ReceivedByServer, SentToServer,
+};
+
+const ALL: [MessageStatus; 6] = [
+    SentToServer,
+    ReceivedByServer,
+    ReceivedByPeer,
+    ReadByPeer,
+    MessageFromPeer,
+    FailedToSend,
+];
+
+#[test]
+fn any_status_replaces_none() {
+    for status in ALL {
+        assert!(status.replaces(None), "{:?}", status);
+    }
+}
+
+#[test]
+fn a_status_can_be_set_again() {
+    for status in ALL {
+        assert!(status.replaces(Some(status)), "{:?}", status);
+    }
+}
+
+#[test]
+fn receipts_move_the_status_forward() {
+    let order = [SentToServer, ReceivedByServer, ReceivedByPeer, ReadByPeer];
+    for (i, old) in order.iter().enumerate() {
+        for (j, new) in order.iter().enumerate() {
+            assert_eq!(new.replaces(Some(*old)), j >= i, "{:?} over {:?}", new, old);
+        }
+    }
+}
+
+#[test]
+fn a_late_server_ack_does_not_undo_the_peer_receipts() {
+    assert!(!ReceivedByServer.replaces(Some(ReceivedByPeer)));
+    assert!(!ReceivedByServer.replaces(Some(ReadByPeer)));
+    assert!(!ReceivedByPeer.replaces(Some(ReadByPeer)));
+}
+
+#[test]
+fn a_failure_is_undone_by_any_sign_the_message_went_through() {
+    assert!(FailedToSend.replaces(Some(SentToServer)));
+    assert!(!SentToServer.replaces(Some(FailedToSend)));
+    for status in [ReceivedByServer, ReceivedByPeer, ReadByPeer] {
+        assert!(status.replaces(Some(FailedToSend)), "{:?}", status);
+        assert!(!FailedToSend.replaces(Some(status)), "{:?}", status);
+    }
+}
@@ -0,0 +1,153 @@
+use client_lib::auth::Auth;
+use client_lib::codec::WireCodec;
+use client_lib::communication::{handshake, Capability, Connection, TUICommand, TUIEvent};
+use client_lib::transport::{memory_listener, memory_pair, Endpoint, Listener, Transport};
+use client_lib::ClientError;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+const CAPABILITIES: &[Capability] = &[Capability::Drawings, Capability::BinaryCodec];
+
+//handshakes both ends at once, the hellos cross
+fn connect(a: Box<dyn Transport>, b: Box<dyn Transport>) -> (Connection, Connection) {
+    let other = thread::spawn(move || handshake(b, CAPABILITIES, Auth::Open).unwrap());
+    let ours = handshake(a, CAPABILITIES, Auth::Open).unwrap();
+    (ours, other.join().unwrap())
+}
+
+fn assert_link_works(mut frontend: Connection, mut backend: Connection) {
+    assert_eq!(frontend.codec(), WireCodec::Binary);
+    frontend.send(TUIEvent::RequestRoomList(3)).unwrap();
+    assert_eq!(
+        backend.receive::<TUIEvent>().unwrap(),
+        TUIEvent::RequestRoomList(3)
+    );
+    backend.send(TUICommand::Kill).unwrap();
+    assert_eq!(frontend.receive::<TUICommand>().unwrap(), TUICommand::Kill);
+
+    frontend.shutdown();
+    assert!(matches!(
+        backend.receive::<TUIEvent>(),
+        Err(ClientError::ConnectionClosed)
+    ));
+}
+
+#[test]
+fn memory_pair_carries_a_connection() {
+    let (a, b) = memory_pair();
+    let (frontend, backend) = connect(Box::new(a), Box::new(b));
+    assert_link_works(frontend, backend);
+}
+
+#[test]
+fn memory_stream_ends_when_the_other_side_is_dropped() {
+    let (mut a, b) = memory_pair();
+    let clone = b.try_clone_boxed().unwrap();
+    drop(b);
+    //a clone keeps the stream open
+    a.write_all(b"still there").unwrap();
+    drop(clone);
+
+    let mut buffer = Vec::new();
+    a.read_to_end(&mut buffer).unwrap();
+    assert!(buffer.is_empty());
+    assert_eq!(a.write(b"gone").unwrap_err().kind(), ErrorKind::BrokenPipe);
+}
+
+#[test]
+fn memory_stream_read_times_out() {
+    let (mut a, _b) = memory_pair();
+    a.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+    let error = a.read(&mut [0u8; 4]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn memory_listener_accepts_connectors() {
+    let (listener, connector) = memory_listener();
+    assert_eq!(listener.endpoint(), None);
+    let frontend = thread::spawn(move || connector.connect().unwrap());
+    let backend = listener.accept_transport().unwrap();
+    let (backend, frontend) = connect(backend, Box::new(frontend.join().unwrap()));
+    assert_link_works(frontend, backend);
+}
+
+#[test]
+fn memory_listener_stops_without_connectors() {
+    let (listener, connector) = memory_listener();
+    drop(connector);
+    assert!(listener.accept_transport().is_err());
+}
+
+#[test]
+fn listeners_are_woken_up_from_another_thread() {
+    let (listener, _connector) = memory_listener();
+    let listeners: Vec<Box<dyn Listener>> = vec![
+        Box::new(listener),
+        Box::new(TcpListener::bind("127.0.0.1:0").unwrap()),
+    ];
+    for listener in listeners {
+        let waker = listener.waker();
+        let accepting = thread::spawn(move || listener.accept_transport().is_ok());
+        waker();
+        assert!(accepting.join().unwrap());
+    }
+}
+
+#[test]
+fn tcp_endpoint_carries_a_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = listener.endpoint().unwrap();
+    let frontend = endpoint.connect().unwrap();
+    let backend = listener.accept_transport().unwrap();
+    let (frontend, backend) = connect(frontend, backend);
+    assert_link_works(frontend, backend);
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket_is_private_and_removed_on_drop() {
+    use client_lib::transport::UnixSocketListener;
+    use std::os::unix::fs::PermissionsExt;
+
+    let listener = UnixSocketListener::bind_private().unwrap();
+    let path = listener.path().to_path_buf();
+    let mode = |path: &std::path::Path| path.metadata().unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(mode(path.parent().unwrap()), 0o700);
+
+    let endpoint = listener.endpoint().unwrap();
+    let frontend = endpoint.connect().unwrap();
+    let backend = listener.accept_transport().unwrap();
+    let (frontend, backend) = connect(frontend, backend);
+    assert_link_works(frontend, backend);
+
+    drop(listener);
+    assert!(!path.exists());
+    assert!(!path.parent().unwrap().exists());
+}
+
+#[test]
+fn endpoints_parse_and_print() {
+    let tcp = "tcp:127.0.0.1:4000".parse::<Endpoint>().unwrap();
+    assert_eq!(tcp, Endpoint::Tcp(([127, 0, 0, 1], 4000).into()));
+    assert_eq!(tcp.to_string(), "tcp:127.0.0.1:4000");
+    //a bare port is what older backends pass
+    assert_eq!("4000".parse::<Endpoint>().unwrap(), tcp);
+
+    #[cfg(unix)]
+    {
+        let unix = "unix:/tmp/some dir/tui.sock".parse::<Endpoint>().unwrap();
+        assert_eq!(unix, Endpoint::Unix("/tmp/some dir/tui.sock".into()));
+        assert_eq!(unix.to_string().parse::<Endpoint>().unwrap(), unix);
+    }
+
+    for invalid in ["", "unix:", "tcp:nowhere", "carrier-pigeon:home"] {
+        assert!(matches!(
+            invalid.parse::<Endpoint>(),
+            Err(ClientError::InvalidEndpoint(_))
+        ));
+    }
+}